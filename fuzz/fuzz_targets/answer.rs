@@ -0,0 +1,22 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libgtp::model::{Answer, CommandName};
+
+fuzz_target!(|data: &[u8]| {
+    if data.is_empty() {
+        return;
+    }
+    let Ok(s) = core::str::from_utf8(&data[1..]) else {
+        return;
+    };
+
+    let _ = Answer::parse_answer(s);
+
+    // the first byte picks the command the answer is decoded for
+    let names = ["protocol_version", "name", "known_command", "list_commands", "genmove",
+                 "fixed_handicap", "final_score", "final_status_list", "get_komi",
+                 "kata-genmove_analyze", "play", "unknown"];
+    let name = CommandName::from(names[data[0] as usize % names.len()].to_string());
+    let _ = Answer::parse_for(name, s);
+});
@@ -0,0 +1,18 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libgtp::model::{Command, CommandReader, GtpCommand};
+
+fuzz_target!(|data: &[u8]| {
+    for command in CommandReader::new(data.iter().copied()) {
+        let _ = command.map(|c| c.to_string());
+    }
+
+    if let Ok(s) = core::str::from_utf8(data) {
+        if let Ok(command) = s.parse::<Command>() {
+            let _ = command.to_string();
+            let _ = GtpCommand::try_from(&command);
+        }
+        let _ = s.parse::<GtpCommand>().map(|c| c.to_string());
+    }
+});
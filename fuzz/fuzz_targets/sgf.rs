@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use libgtp::sgf::parse_collection;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(s) = core::str::from_utf8(data) {
+        for tree in parse_collection(s).into_iter().flatten() {
+            let _ = tree.to_string();
+            let _ = tree.to_game();
+        }
+    }
+});
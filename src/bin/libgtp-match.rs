@@ -0,0 +1,278 @@
+//! Plays matches between two GTP engines, like gogui-twogtp.
+//!
+//! ```text
+//! libgtp-match --first "katago gtp -model a.bin.gz" --second "gnugo --mode gtp" --games 20 --sgf-dir games
+//! ```
+
+use libgtp::engine::KATAGO_READY_LINE;
+use libgtp::model::{Color, Game, GameClock, GtpCommand, Move, RectSize, Score, TimeControl, Vertex, WinReason};
+use libgtp::sgf::GameTree;
+use libgtp::{Controller, Engine};
+use std::fmt::Display;
+use std::io;
+use std::path::PathBuf;
+use std::process::exit;
+use std::time::Instant;
+
+const USAGE: &str = "usage: libgtp-match --first <command> --second <command> [options]
+
+options:
+    --games <n>       number of games to play (default 10)
+    --size <n>        board size (default 19)
+    --komi <k>        komi (default 7.5)
+    --max-moves <n>   moves after which a game is void (default 1000)
+    --time <control>  time control in seconds: absolute <main>, canadian <main> <period> <stones>,
+                      byoyomi <main> <period> <periods> or fischer <main> <increment> (default none)
+    --no-alternate    the first engine always plays black
+    --sgf-dir <dir>   write every game as <dir>/game-<n>.sgf
+    --katago          wait for KataGo's ready line before sending commands";
+
+struct Options {
+    first: String,
+    second: String,
+    games: u32,
+    size: u32,
+    komi: f32,
+    max_moves: usize,
+    time: TimeControl,
+    alternate: bool,
+    sgf_dir: Option<PathBuf>,
+    katago: bool,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        first: String::new(),
+        second: String::new(),
+        games: 10,
+        size: 19,
+        komi: 7.5,
+        max_moves: 1000,
+        time: TimeControl::None,
+        alternate: true,
+        sgf_dir: None,
+        katago: false,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--first" => options.first = value()?,
+            "--second" => options.second = value()?,
+            "--games" => options.games = value()?.parse().map_err(|e| format!("--games: {}", e))?,
+            "--size" => options.size = value()?.parse().map_err(|e| format!("--size: {}", e))?,
+            "--komi" => options.komi = value()?.parse().map_err(|e| format!("--komi: {}", e))?,
+            "--max-moves" => options.max_moves = value()?.parse().map_err(|e| format!("--max-moves: {}", e))?,
+            "--time" => options.time = value()?.parse().map_err(|e| format!("--time: {}", e))?,
+            "--sgf-dir" => options.sgf_dir = Some(value()?.into()),
+            "--no-alternate" => options.alternate = false,
+            "--katago" => options.katago = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0);
+            },
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    if options.first.trim().is_empty() || options.second.trim().is_empty() {
+        return Err("both --first and --second are required".to_string());
+    }
+    if !RectSize::square(options.size).is_valid() {
+        return Err(format!("invalid board size {}", options.size));
+    }
+    Ok(options)
+}
+
+struct Player {
+    name: String,
+    controller: Controller,
+}
+
+impl Player {
+    fn start(command: &str, katago: bool) -> io::Result<Self> {
+        let mut words = command.split_whitespace();
+        let program = words.next().unwrap_or_default();
+        let args: Vec<&str> = words.collect();
+        let engine = Engine::new(program, &args)?
+            .with_ready_line(if katago { Some(KATAGO_READY_LINE) } else { None })
+            .start();
+
+        let mut controller = Controller::from_engine(engine);
+        let name = match controller.run(GtpCommand::Name)?.data() {
+            Some(data) => data.to_string().trim().to_string(),
+            None => program.to_string(),
+        };
+        Ok(Self { name, controller })
+    }
+
+    fn setup(&mut self, game: &Game, time: TimeControl) -> io::Result<()> {
+        for command in game.to_commands() {
+            self.controller.run(command)?;
+        }
+        if time != TimeControl::None {
+            self.controller.send_time_settings(time)?;
+        }
+        Ok(())
+    }
+
+    fn final_score(&mut self) -> Option<Score> {
+        let response = self.controller.run(GtpCommand::FinalScore).ok()?;
+        response.data().as_ref()?.as_score().copied()
+    }
+}
+
+/// The result of `player` of `color` failing to go on with the game.
+fn forfeit(player: &Player, color: Color, e: impl Display) -> Score {
+    eprintln!("{} forfeits: {}", player.name, e);
+    Score::Win { color: color.opponent(), margin: None, reason: WinReason::Forfeit }
+}
+
+/// Plays one game, `players[0]` being black. Returns the game and its result,
+/// an engine failing to set up, to move or to follow the game losing by forfeit.
+fn play_game(players: &mut [&mut Player; 2], options: &Options) -> (Game, Score) {
+    let mut game = Game::new(RectSize::square(options.size)).with_komi(options.komi);
+    let mut clock = GameClock::new(options.time);
+    for (player, color) in players.iter_mut().zip([Color::Black, Color::White]) {
+        if let Err(e) = player.setup(&game, options.time) {
+            return (game, forfeit(player, color, e));
+        }
+    }
+
+    while !game.is_over() {
+        if game.move_number() >= options.max_moves {
+            eprintln!("move limit reached");
+            return (game, Score::Void);
+        }
+
+        let color = game.to_move();
+        let (mover, other) = match color {
+            Color::Black => (0, 1),
+            Color::White => (1, 0),
+        };
+        if let Err(e) = players[mover].controller.send_time_left(&clock, color) {
+            return (game, forfeit(players[mover], color, e));
+        }
+        let start = Instant::now();
+        let response = match players[mover].controller.run(GtpCommand::Genmove(color)) {
+            Ok(response) => response,
+            Err(e) => return (game, forfeit(players[mover], color, e)),
+        };
+        if !clock.record_move(color, start.elapsed()) {
+            return (game, Score::Win { color: color.opponent(), margin: None, reason: WinReason::Time });
+        }
+        let vertex = match response.data().as_ref().and_then(|d| d.as_vertex()) {
+            Some(vertex) => *vertex,
+            None => return (game, forfeit(players[mover], color, "genmove answered without a vertex")),
+        };
+        let mov = Move { color, vertex };
+
+        if vertex == Vertex::Resign {
+            let _ = game.play(mov);
+            return (game, Score::Win { color: color.opponent(), margin: None, reason: WinReason::Resign });
+        }
+        if let Err(e) = game.play(mov) {
+            return (game, forfeit(players[mover], color, format!("played {}: {}", mov, e)));
+        }
+        if let Err(e) = players[other].controller.run(GtpCommand::Play(mov)) {
+            return (game, forfeit(players[other], color.opponent(), format!("refused {}: {}", mov, e)));
+        }
+    }
+
+    let scores = [players[0].final_score(), players[1].final_score()];
+    let score = match scores {
+        [Some(a), Some(b)] if a == b => a,
+        [Some(a), None] | [None, Some(a)] => a,
+        [a, b] => {
+            eprintln!("engines disagree on the result: {:?} and {:?}", a, b);
+            Score::Void
+        },
+    };
+    (game, score)
+}
+
+/// 95% Wilson score interval of a proportion of `successes` among `n`.
+fn wilson_interval(successes: f64, n: f64) -> (f64, f64) {
+    if n == 0.0 {
+        return (0.0, 1.0);
+    }
+    let z = 1.96_f64;
+    let p = successes / n;
+    let denominator = 1.0 + z * z / n;
+    let center = (p + z * z / (2.0 * n)) / denominator;
+    let half = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / denominator;
+    ((center - half).max(0.0), (center + half).min(1.0))
+}
+
+fn run(options: Options) -> io::Result<()> {
+    let mut first = Player::start(&options.first, options.katago)?;
+    let mut second = Player::start(&options.second, options.katago)?;
+    if let Some(dir) = &options.sgf_dir {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    // wins of the first engine, of the second one, draws and void games
+    let mut wins = [0u32; 2];
+    let mut draws = 0;
+    let mut void = 0;
+    let mut black_wins = 0;
+
+    for n in 1..=options.games {
+        let first_is_black = !options.alternate || n % 2 == 1;
+        let mut players = if first_is_black { [&mut first, &mut second] } else { [&mut second, &mut first] };
+        let (game, score) = play_game(&mut players, &options);
+
+        println!("game {}: {} (B) vs {} (W): {}", n, players[0].name, players[1].name, score);
+        match score.winner() {
+            Some(winner) => {
+                let first_won = (winner == Color::Black) == first_is_black;
+                wins[if first_won { 0 } else { 1 }] += 1;
+                if winner == Color::Black {
+                    black_wins += 1;
+                }
+            },
+            None if score.is_draw() => draws += 1,
+            None => void += 1,
+        }
+
+        if let Some(dir) = &options.sgf_dir {
+            let mut tree = GameTree::from_game(&game);
+            let root = tree.root_mut();
+            root.set("PB", players[0].name.clone());
+            root.set("PW", players[1].name.clone());
+            root.set("RE", score.to_string());
+            std::fs::write(dir.join(format!("game-{:03}.sgf", n)), format!("{}\n", tree))?;
+        }
+    }
+
+    let counted = wins[0] + wins[1] + draws;
+    let (low, high) = wilson_interval(wins[0] as f64 + draws as f64 / 2.0, counted as f64);
+    println!();
+    println!("{}: {} wins", first.name, wins[0]);
+    println!("{}: {} wins", second.name, wins[1]);
+    println!("draws: {}, void: {}, black wins: {}", draws, void, black_wins);
+    if counted > 0 {
+        let rate = (wins[0] as f64 + draws as f64 / 2.0) / counted as f64;
+        println!("{} win rate: {:.1}% (95% CI {:.1}% - {:.1}%)", first.name, rate * 100.0, low * 100.0, high * 100.0);
+    }
+
+    let _ = first.controller.run(GtpCommand::Quit);
+    let _ = second.controller.run(GtpCommand::Quit);
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            exit(2);
+        },
+    };
+
+    if let Err(e) = run(options) {
+        eprintln!("error: {}", e);
+        exit(1);
+    }
+}
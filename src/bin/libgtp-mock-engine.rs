@@ -0,0 +1,34 @@
+//! Scripted GTP engine for tests, see [`libgtp::mock`] for the script format.
+//!
+//! Exits with 2 when a command did not match the script and with 3 on `@crash`.
+
+use libgtp::mock::{MockEngine, MockExit};
+use std::io;
+use std::process::exit;
+
+fn main() {
+    let path = match std::env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: libgtp-mock-engine <script>");
+            exit(2);
+        },
+    };
+    let mut engine = match MockEngine::from_file(&path) {
+        Ok(engine) => engine,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            exit(2);
+        },
+    };
+
+    match engine.run(io::stdin().lock(), io::stdout(), io::stderr()) {
+        Ok(MockExit::Crash) => exit(3),
+        Ok(_) if !engine.mismatches().is_empty() => exit(2),
+        Ok(_) => {},
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(1);
+        },
+    }
+}
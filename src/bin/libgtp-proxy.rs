@@ -0,0 +1,233 @@
+//! Sits between a GUI and an engine, relaying GTP both ways and writing a
+//! timestamped transcript of the session.
+//!
+//! ```text
+//! libgtp-proxy --rename lz-analyze=kata-analyze --init "kata-set-param maxVisits 400" --log session.log -- katago gtp
+//! ```
+
+use libgtp::engine::KATAGO_READY_LINE;
+use libgtp::model::{preprocess, Command, CommandName};
+use libgtp::{Controller, Engine};
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::process::exit;
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const USAGE: &str = "usage: libgtp-proxy [options] -- <engine command>
+
+options:
+    --rename <from>=<to>   send the GUI's <from> commands to the engine as <to>
+    --init <command>       send <command> to the engine on startup, can be repeated
+    --log <file>           write the transcript to <file> instead of stderr
+    --record <file>        save the session to <file> for replaying it, see libgtp::transcript
+    --katago               wait for KataGo's ready line before sending commands";
+
+/// How often the engine's output is relayed while waiting for the GUI.
+const RELAY_POLL: Duration = Duration::from_millis(2);
+
+struct Options {
+    engine: Vec<String>,
+    renames: Vec<(String, String)>,
+    init: Vec<String>,
+    log: Option<String>,
+    record: Option<String>,
+    katago: bool,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut options = Options {
+        engine: Vec::new(),
+        renames: Vec::new(),
+        init: Vec::new(),
+        log: None,
+        record: None,
+        katago: false,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+        match arg.as_str() {
+            "--rename" => {
+                let rule = value()?;
+                let (from, to) = rule.split_once('=').ok_or(format!("invalid rename rule {}", rule))?;
+                options.renames.push((from.to_string(), to.to_string()));
+            },
+            "--init" => options.init.push(value()?),
+            "--log" => options.log = Some(value()?),
+            "--record" => options.record = Some(value()?),
+            "--katago" => options.katago = true,
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                exit(0);
+            },
+            "--" => {
+                options.engine = args.collect();
+                break;
+            },
+            _ => return Err(format!("unknown argument {}", arg)),
+        }
+    }
+
+    if options.engine.is_empty() {
+        return Err("missing engine command".to_string());
+    }
+    Ok(options)
+}
+
+/// Session log, one line per message with the time elapsed since startup.
+struct SessionLog {
+    out: Box<dyn Write>,
+    start: Instant,
+}
+
+impl SessionLog {
+    fn new(out: Box<dyn Write>) -> Self {
+        let mut session = Self { out, start: Instant::now() };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        session.note(&format!("session started at unix time {}.{:03}", now.as_secs(), now.subsec_millis()));
+        session
+    }
+
+    fn log(&mut self, direction: &str, text: &str) {
+        let elapsed = self.start.elapsed();
+        for line in text.trim_end_matches('\n').split('\n') {
+            let _ = writeln!(self.out, "[{:>5}.{:03}] {} {}", elapsed.as_secs(), elapsed.subsec_millis(), direction, line);
+        }
+        let _ = self.out.flush();
+    }
+
+    fn note(&mut self, text: &str) {
+        self.log("#", text);
+    }
+}
+
+/// Applies the rename rules to the command name of a preprocessed line.
+fn rewrite(line: &str, renames: &[(String, String)]) -> String {
+    let mut tokens: Vec<&str> = line.split_ascii_whitespace().collect();
+    let name = if tokens.first().map(|t| t.parse::<u32>().is_ok()).unwrap_or(false) { 1 } else { 0 };
+    if let Some(token) = tokens.get_mut(name) {
+        if let Some((_, to)) = renames.iter().find(|(from, _)| from == token) {
+            *token = to;
+        }
+    }
+    tokens.join(" ")
+}
+
+fn run(options: Options) -> io::Result<()> {
+    let log: Box<dyn Write> = match &options.log {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stderr()),
+    };
+    let mut session = SessionLog::new(log);
+
+    let args: Vec<&str> = options.engine[1..].iter().map(|a| a.as_str()).collect();
+    let engine = Engine::new(&options.engine[0], &args)?
+        .with_ready_line(if options.katago { Some(KATAGO_READY_LINE) } else { None })
+        .with_raw_output(true)
+        .start();
+    let mut controller = Controller::from_engine(engine);
+    if options.record.is_some() {
+        controller.start_recording();
+    }
+    session.note(&format!("engine: {}", options.engine.join(" ")));
+
+    for init in &options.init {
+        let command: Command = init.parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("--init {}: {}", init, e)))?;
+        session.log("proxy>", &command.to_string());
+        let answer = controller.send_command_raw(&command)?;
+        session.log("<engine", &answer);
+    }
+
+    let (sender, lines) = mpsc::channel();
+    std::thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            match line {
+                Ok(line) => if sender.send(line).is_err() { break },
+                Err(_) => break,
+            }
+        }
+    });
+
+    let mut stdout = io::stdout();
+    // answers not started yet, and whether the next line may start one
+    let mut pending = 0usize;
+    let mut answer_start = true;
+    let mut quitting = false;
+    'session: loop {
+        // every engine line is relayed as is, info lines included
+        loop {
+            let line = match controller.try_read_line() {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(_) => {
+                    session.note("engine closed its output");
+                    break 'session;
+                },
+            };
+            session.log("<engine", &line);
+            writeln!(stdout, "{}", line)?;
+            if line.is_empty() {
+                answer_start = true;
+                if quitting && pending == 0 {
+                    stdout.flush()?;
+                    break 'session;
+                }
+            } else if answer_start && line.starts_with(['=', '?']) {
+                pending = pending.saturating_sub(1);
+                answer_start = false;
+            }
+        }
+        stdout.flush()?;
+
+        let line = match lines.recv_timeout(RELAY_POLL) {
+            Ok(line) => line,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            // relays the answers to quit until the engine is gone
+            Err(mpsc::RecvTimeoutError::Disconnected) if quitting => {
+                std::thread::sleep(RELAY_POLL);
+                continue;
+            },
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        };
+        let line = preprocess(&line);
+        if line.trim().is_empty() {
+            continue;
+        }
+        session.log("gui>", &line);
+
+        let rewritten = rewrite(&line, &options.renames);
+        if rewritten != line.trim() {
+            session.note(&format!("rewritten as {}", rewritten));
+        }
+        match rewritten.parse::<Command>() {
+            Ok(command) => quitting |= command.name() == CommandName::Quit,
+            Err(e) => session.note(&format!("passed through unparsed: {}", e)),
+        }
+        controller.send_line_raw(&rewritten)?;
+        pending += 1;
+    }
+
+    session.note("session ended");
+    if let (Some(path), Some(recording)) = (&options.record, controller.take_transcript()) {
+        recording.save(path)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            exit(2);
+        },
+    };
+
+    if let Err(e) = run(options) {
+        eprintln!("error: {}", e);
+        exit(1);
+    }
+}
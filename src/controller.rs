@@ -1,13 +1,31 @@
 use std::io::Write;
 use crate::model::Command;
+use crate::model::CommandName;
 use crate::model::Answer;
+use crate::model::Board;
+use crate::model::Color;
+use crate::model::Game;
+use crate::model::GameClock;
+use crate::model::GtpCommand;
+use crate::model::RectSize;
+use crate::model::Response;
+use crate::model::TimeControl;
+use crate::sgf::GameTree;
+use crate::transcript::Transcript;
 use crate::Engine;
+use core::convert::TryFrom;
 use log::error;
+use log::warn;
 
 #[derive(Debug)]
 pub struct Controller {
     /// link to engine
     engine: Engine,
+    /// local copy of the engine's position, when mirroring is enabled
+    mirror: Option<Game>,
+    in_sync: bool,
+    /// answer to `list_commands`, once asked
+    commands: Option<Vec<CommandName>>,
 }
 
 impl Controller {
@@ -20,16 +38,194 @@ impl Controller {
             },
         };
 
+        Self::from_engine(engine)
+    }
+
+    /// Drives an already started engine.
+    pub fn from_engine(engine: Engine) -> Self {
         Self {
             engine,
+            mirror: None,
+            in_sync: true,
+            commands: None,
         }
     }
 
-    pub fn send_command(&mut self, command: Command) -> Result<Answer, std::io::Error>{
-        self.engine.write(command.to_string().as_bytes())?;
+    /// Starts following the engine's position, from an empty 19x19 board.
+    /// Every successful command changing the position updates the mirror.
+    pub fn enable_mirroring(&mut self) {
+        self.enable_mirroring_from(Game::new(RectSize::square(19)));
+    }
+
+    /// Starts following the engine's position, assuming it currently matches `game`.
+    pub fn enable_mirroring_from(&mut self, game: Game) {
+        self.mirror = Some(game);
+        self.in_sync = true;
+    }
+
+    pub fn disable_mirroring(&mut self) {
+        self.mirror = None;
+        self.in_sync = true;
+    }
+
+    pub const fn mirror(&self) -> Option<&Game> {
+        self.mirror.as_ref()
+    }
+
+    pub fn board(&self) -> Option<&Board> {
+        self.mirror.as_ref().map(|g| g.board())
+    }
+
+    pub fn move_number(&self) -> Option<usize> {
+        self.mirror.as_ref().map(|g| g.move_number())
+    }
+
+    pub fn to_move(&self) -> Option<Color> {
+        self.mirror.as_ref().map(|g| g.to_move())
+    }
+
+    /// `false` once the engine accepted a command the mirror could not follow,
+    /// e.g. a move the local board finds illegal or a `loadsgf`.
+    pub const fn is_in_sync(&self) -> bool {
+        self.in_sync
+    }
+
+    /// Starts recording the session, see [`crate::transcript`].
+    pub fn start_recording(&mut self) {
+        self.engine.record();
+    }
+
+    /// Stops recording, returning the session recorded so far.
+    pub fn take_transcript(&mut self) -> Option<Transcript> {
+        self.engine.take_transcript()
+    }
+
+    /// Sends `command` and returns the engine's answer as received, up to and
+    /// including the terminating empty line. Analysis commands only return
+    /// their first line; the following info lines are read with
+    /// [`Controller::read_info`] or [`Controller::take_info_line`].
+    pub fn send_command_raw(&mut self, command: &Command) -> Result<String, std::io::Error> {
+        self.engine.write_all(command.to_string().as_bytes())?;
         self.engine.flush()?;
-        let s = self.engine.read_line();
-        Ok(Answer::parse_answer(s.as_str()).unwrap())
+        Ok(match command.name() {
+            // analysis commands answer with a bare "=" line followed by info lines
+            CommandName::LzAnalyze |
+            CommandName::KataAnalyze |
+            CommandName::Analyze => self.engine.read_line()?,
+            _ => self.engine.read_response()?,
+        })
+    }
+
+    /// Writes `line` to the engine as is, e.g. a command the crate does not
+    /// model, without reading the answer. Its lines are then read with
+    /// [`Controller::try_read_line`].
+    pub fn send_line_raw(&mut self, line: &str) -> Result<(), std::io::Error> {
+        self.engine.write_all(line.trim_end().as_bytes())?;
+        self.engine.write_all(b"\n")?;
+        self.engine.flush()
+    }
+
+    /// The next line the engine wrote, if any arrived yet.
+    pub fn try_read_line(&mut self) -> Result<Option<String>, std::io::Error> {
+        self.engine.try_read_line()
+    }
+
+    pub fn send_command(&mut self, command: Command) -> Result<Answer, std::io::Error>{
+        let s = self.send_command_raw(&command)?;
+        let answer = Answer::parse_for(command.name(), s.as_str())
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        if let (Some(game), Answer::Response(response)) = (&mut self.mirror, &answer) {
+            if let Ok(typed) = GtpCommand::try_from(&command) {
+                if !game.apply(&typed, response.data().as_ref()) {
+                    warn!("mirrored position lost track of the engine after {}", typed.name());
+                    self.in_sync = false;
+                }
+            }
+        }
+
+        Ok(answer)
+    }
+
+    /// Sends `command`, turning a failure answer into an error.
+    pub fn run(&mut self, command: impl Into<Command>) -> Result<Response, std::io::Error> {
+        let command = command.into();
+        let name = command.name();
+        match self.send_command(command)? {
+            Answer::Response(response) => Ok(response),
+            Answer::Failure(failure) =>
+                Err(std::io::Error::other(format!("{} failed: {}", name, failure.message()))),
+            Answer::Info(_) =>
+                Err(std::io::Error::new(std::io::ErrorKind::InvalidData, format!("unexpected info line after {}", name))),
+        }
+    }
+
+    /// The commands advertised by the engine. `list_commands` is only sent once.
+    pub fn supported_commands(&mut self) -> Result<&[CommandName], std::io::Error> {
+        if self.commands.is_none() {
+            let response = self.run(GtpCommand::ListCommands)?;
+            let names = response.data().as_ref()
+                .and_then(|d| d.as_command_names())
+                .cloned()
+                .unwrap_or_default();
+            self.commands = Some(names);
+        }
+        Ok(self.commands.as_deref().unwrap_or_default())
+    }
+
+    pub fn supports(&mut self, name: CommandName) -> Result<bool, std::io::Error> {
+        Ok(self.supported_commands()?.contains(&name))
+    }
+
+    /// Sets up the position of an SGF record without `loadsgf`, replaying it
+    /// with `boardsize`, `clear_board`, `komi`, handicap or `set_position`,
+    /// then `play`. Like `loadsgf`, stops before `move_number` when given.
+    /// Returns the loaded game, which also becomes the mirror when mirroring.
+    pub fn load_sgf(&mut self, tree: &GameTree, move_number: Option<usize>) -> Result<Game, std::io::Error> {
+        let mut game = tree.to_game()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))?;
+        if let Some(n) = move_number {
+            while game.move_number() >= n.max(1) {
+                game.undo();
+            }
+        }
+
+        let commands = if self.supports(CommandName::SetPosition)? {
+            game.to_commands_with_set_position()
+        } else {
+            game.to_commands()
+        };
+
+        // the mirror cannot follow set_position, it is replaced once loaded
+        let mirror = self.mirror.take();
+        for command in commands {
+            if let Err(e) = self.run(command) {
+                self.mirror = mirror;
+                self.in_sync = false;
+                return Err(e);
+            }
+        }
+        if mirror.is_some() {
+            self.enable_mirroring_from(game.clone());
+        }
+
+        Ok(game)
+    }
+
+    /// Sets the time control with `kgs-time_settings` when the engine
+    /// supports it and the control has a KGS form, with `time_settings` otherwise.
+    pub fn send_time_settings(&mut self, control: TimeControl) -> Result<(), std::io::Error> {
+        let kgs = self.supports(CommandName::KgsTimeSettings)?;
+        self.run(control.to_command(kgs)).map(|_| ())
+    }
+
+    /// Tells the engine the time `color` has left on `clock`, usually before
+    /// asking it to move. Does nothing without time control.
+    pub fn send_time_left(&mut self, clock: &GameClock, color: Color) -> Result<(), std::io::Error> {
+        match clock.time_left(color) {
+            Some(command) => self.run(command).map(|_| ()),
+            None => Ok(()),
+        }
     }
 
     pub fn read_info(&self) -> Result<Option<crate::model::Info>, crate::model::ParseError> {
@@ -39,12 +235,21 @@ impl Controller {
         }
         let answer = Answer::parse_answer(s.as_str())?;
         if answer.is_info() {
-            Ok(Some(answer.to_info().unwrap()))
+            let info = answer.to_info().unwrap();
+            Ok(Some(match &self.mirror {
+                Some(game) => info.with_board_size(game.size()),
+                None => info,
+            }))
         } else {
-            Err(crate::model::ParseError::WrongAnswerFormat)
+            Err(crate::model::ParseErrorKind::WrongAnswerFormat.into())
         }
     }
 
+    /// The latest info line received, unparsed. Each line is only returned once.
+    pub fn take_info_line(&mut self) -> Option<String> {
+        Some(self.engine.take_info()).filter(|l| !l.is_empty())
+    }
+
     pub fn discard_info(&mut self) {
         self.engine.discard_info();
     }
@@ -1,16 +1,47 @@
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::sync::Condvar;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::collections::VecDeque;
-use std::io::{ BufReader, BufRead, BufWriter, Write };
-use std::process::ChildStdin;
+use std::io::{ BufReader, BufRead, BufWriter, Read, Write };
+use crate::transcript::Transcript;
+use log::debug;
+
+/// Line KataGo prints on stderr once it accepts commands.
+pub const KATAGO_READY_LINE: &str = "GTP ready, beginning main protocol loop";
+
+type Input = Box<dyn Write + Send>;
+type Output = Box<dyn Read + Send>;
 
-#[derive(Debug)]
 pub struct Engine {
-    child: std::process::Child,
-    stdin: BufWriter<ChildStdin>,
+    child: Option<std::process::Child>,
+    stdin: BufWriter<Input>,
+    /// stdout and stderr, until the engine is started
+    outputs: Option<(Output, Output)>,
     incoming_lines: Arc<Mutex<VecDeque<String>>>,
+    /// notified when a line is queued and when the engine closes its stdout
+    line_queued: Arc<Condvar>,
     analyze_line: Arc<Mutex<String>>,
     pub is_ready: Arc<Mutex<bool>>,
+    ready_line: Option<String>,
+    /// info lines are also kept among the answer lines
+    raw_output: bool,
+    /// set once the engine closed its stdout
+    closed: Arc<AtomicBool>,
+    transcript: Arc<Mutex<Option<Transcript>>>,
+}
+
+impl std::fmt::Debug for Engine {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("Engine")
+            .field("child", &self.child)
+            .field("incoming_lines", &self.incoming_lines)
+            .field("analyze_line", &self.analyze_line)
+            .field("is_ready", &self.is_ready)
+            .field("ready_line", &self.ready_line)
+            .field("closed", &self.closed)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Engine {
@@ -22,64 +53,181 @@ impl Engine {
             .stderr(std::process::Stdio::piped())
             .spawn()?;
 
-        let stdin  = BufWriter::new(child.stdin.take().unwrap());
-        Ok(Self {
-            child,
-            stdin,
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let stderr = child.stderr.take().unwrap();
+        let mut engine = Self::from_streams(stdin, stdout, stderr)
+            .with_ready_line(Some(KATAGO_READY_LINE));
+        engine.child = Some(child);
+        Ok(engine)
+    }
+
+    /// An engine reached through arbitrary streams instead of a child
+    /// process, e.g. a socket or an in-process fake. It is ready right away.
+    pub fn from_streams(
+        stdin: impl Write + Send + 'static,
+        stdout: impl Read + Send + 'static,
+        stderr: impl Read + Send + 'static,
+    ) -> Self {
+        Self {
+            child: None,
+            stdin: BufWriter::new(Box::new(stdin)),
+            outputs: Some((Box::new(stdout), Box::new(stderr))),
             incoming_lines: Arc::new(Mutex::new(VecDeque::new())),
+            line_queued: Arc::new(Condvar::new()),
             analyze_line: Arc::new(Mutex::new("".to_string())),
             is_ready: Arc::new(Mutex::new(false)),
-        })
+            ready_line: None,
+            raw_output: false,
+            closed: Arc::new(AtomicBool::new(false)),
+            transcript: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Sets the stderr line after which the engine accepts commands.
+    /// With `None`, commands are sent right away, as most engines allow.
+    pub fn with_ready_line(mut self, line: Option<&str>) -> Self {
+        self.ready_line = line.map(|l| l.to_string());
+        self
+    }
+
+    /// Also queues info lines with the answer lines returned by
+    /// [`read_line`](Self::read_line), to relay the engine's output in order.
+    /// Otherwise only the latest info line is kept.
+    pub fn with_raw_output(mut self, raw: bool) -> Self {
+        self.raw_output = raw;
+        self
     }
 
     pub fn start(mut self) -> Self {
         let is_ready = self.is_ready.clone();
+        let ready_line = self.ready_line.clone();
+        if ready_line.is_none() {
+            *is_ready.lock().unwrap() = true;
+        }
 
-        let stdout = BufReader::new(self.child.stdout.take().unwrap());
+        let (stdout, stderr) = match self.outputs.take() {
+            Some(outputs) => outputs,
+            None => return self,
+        };
+        let stdout = BufReader::new(stdout);
         let lines = self.incoming_lines.clone();
         let line = self.analyze_line.clone();
-        let stderr = BufReader::new(self.child.stderr.take().unwrap());
+        let stderr = BufReader::new(stderr);
+        let closed = self.closed.clone();
+        let transcript = self.transcript.clone();
         std::thread::spawn(move || {
-            for l in stderr.lines().map(|x| x.unwrap()) {
-                eprintln!("{}", l);
-                if l == "GTP ready, beginning main protocol loop".to_string() {
+            for l in stderr.lines().map_while(Result::ok) {
+                debug!("stderr: {}", l);
+                if let Some(transcript) = transcript.lock().unwrap().as_mut() {
+                    transcript.stderr(&l);
+                }
+                if ready_line.as_deref() == Some(l.as_str()) {
                     let mut is_ready = is_ready.lock().unwrap();
                     *is_ready = true;
                 }
             }
+            // the engine is gone, let writes fail instead of waiting forever
+            *is_ready.lock().unwrap() = true;
         });
+        let transcript = self.transcript.clone();
+        let raw = self.raw_output;
+        let line_queued = self.line_queued.clone();
         std::thread::spawn(move || {
-            for l in stdout.lines().map(|x| x.unwrap()) {
-                if !l.is_empty() {
-                    if l.as_bytes()[0] == b'i' {
-                        let mut line = line.lock().unwrap();
-                        *line = l;
-                    } else {
-                        let mut lines = lines.lock().unwrap();
-                        println!("{}", l);
-                        lines.push_back(l);
-                    }
+            for l in stdout.lines().map_while(Result::ok) {
+                if let Some(transcript) = transcript.lock().unwrap().as_mut() {
+                    transcript.received(&l);
+                }
+                if l.starts_with("info") && !raw {
+                    let mut line = line.lock().unwrap();
+                    *line = l;
+                } else {
+                    let mut lines = lines.lock().unwrap();
+                    debug!("<- {}", l);
+                    lines.push_back(l);
+                    line_queued.notify_all();
                 }
             }
+            // under the lock, so a reader cannot miss it between its check and its wait
+            let _lines = lines.lock().unwrap();
+            closed.store(true, Ordering::SeqCst);
+            line_queued.notify_all();
         });
 
         self
     }
 
     pub fn kill(mut self) {
-        self.child.kill().unwrap();
+        if let Some(child) = self.child.as_mut() {
+            child.kill().unwrap();
+        }
     }
 
-    pub fn read_line(&mut self) -> String {
-        while self.incoming_lines.lock().unwrap().front().is_none() {}
-        let s = self.incoming_lines.lock().unwrap().pop_front().unwrap();
-        s
+    /// Starts recording everything exchanged with the engine, dropping any
+    /// previous recording.
+    pub fn record(&mut self) {
+        *self.transcript.lock().unwrap() = Some(Transcript::new());
+    }
+
+    /// Stops recording, returning what was recorded.
+    pub fn take_transcript(&mut self) -> Option<Transcript> {
+        self.transcript.lock().unwrap().take()
+    }
+
+    /// Waits for the next line of the engine's answers.
+    /// Fails once the engine closed its output and every line was read.
+    pub fn read_line(&mut self) -> Result<String, std::io::Error> {
+        let mut lines = self.incoming_lines.lock().unwrap();
+        loop {
+            if let Some(line) = lines.pop_front() {
+                return Ok(line);
+            }
+            if self.closed.load(Ordering::SeqCst) {
+                return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "engine closed its output"));
+            }
+            lines = self.line_queued.wait(lines).unwrap();
+        }
+    }
+
+    /// The next line of the engine's answers if one arrived, without waiting.
+    /// Fails once the engine closed its output and every line was read.
+    pub fn try_read_line(&mut self) -> Result<Option<String>, std::io::Error> {
+        if let Some(line) = self.incoming_lines.lock().unwrap().pop_front() {
+            return Ok(Some(line));
+        }
+        if self.closed.load(Ordering::SeqCst) && self.incoming_lines.lock().unwrap().is_empty() {
+            return Err(std::io::Error::new(std::io::ErrorKind::UnexpectedEof, "engine closed its output"));
+        }
+        Ok(None)
+    }
+
+    /// Reads a full response, up to the empty line terminating it.
+    /// Empty lines left over from a previous response are skipped.
+    pub fn read_response(&mut self) -> Result<String, std::io::Error> {
+        let mut response = String::new();
+        loop {
+            let l = self.read_line()?;
+            if l.is_empty() {
+                if response.is_empty() {
+                    continue;
+                }
+                response.push('\n');
+                return Ok(response);
+            }
+            response.push_str(&l);
+            response.push('\n');
+        }
     }
 
     pub fn read_info(&self) -> String {
         self.analyze_line.lock().unwrap().clone()
     }
 
+    /// The latest info line, which is then discarded.
+    pub fn take_info(&mut self) -> String {
+        std::mem::take(&mut *self.analyze_line.lock().unwrap())
+    }
+
     pub fn discard_info(&mut self) {
         let mut line = self.analyze_line.lock().unwrap();
         *line = "".to_string();
@@ -88,14 +236,18 @@ impl Engine {
 
 impl Write for Engine {
     fn write(&mut self, buf: &[u8]) -> Result<usize, std::io::Error> {
-        while *self.is_ready.lock().unwrap() != true {}
+        while !*self.is_ready.lock().unwrap() {}
 
-        println!("{}", String::from_utf8(buf.to_vec()).unwrap());
-        self.stdin.write(buf)
+        let written = self.stdin.write(buf)?;
+        let text = String::from_utf8_lossy(&buf[..written]);
+        debug!("-> {}", text.trim_end());
+        if let Some(transcript) = self.transcript.lock().unwrap().as_mut() {
+            transcript.sent(&text);
+        }
+        Ok(written)
     }
 
     fn flush(&mut self) -> Result<(), std::io::Error> {
         self.stdin.flush()
     }
 }
-
@@ -0,0 +1,483 @@
+//! Client for KataGo's JSON analysis engine (`katago analysis`).
+//!
+//! Queries are written one per line and every analysed turn is answered by a
+//! line carrying the query's id, so many positions can be analysed in one
+//! batch. Candidate moves are returned as [`InfoMove`], as with `kata-analyze`.
+//!
+//! ```no_run
+//! use libgtp::kata_analysis::{AnalysisClient, AnalysisQuery};
+//! use libgtp::model::{Game, RectSize};
+//!
+//! let mut client = AnalysisClient::start("katago", &["analysis", "-config", "analysis.cfg", "-model", "model.bin.gz"]).unwrap();
+//! let game = Game::new(RectSize::square(19)).with_komi(7.5);
+//! let query = AnalysisQuery::from_game("game", &game).with_max_visits(100);
+//! let results = client.analyze(&query).unwrap();
+//! println!("{:?}", results[0].best_move());
+//! ```
+
+use crate::model::{Color, Game, Info, InfoMove, Move, OwnershipMap, RectSize, Vertex};
+use crate::Engine;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::str::FromStr;
+
+/// Line KataGo prints on stderr once it accepts analysis queries.
+pub const KATAGO_ANALYSIS_READY_LINE: &str = "Started, ready to begin handling requests";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisQuery {
+    pub id: String,
+    pub initial_stones: Vec<(Color, Vertex)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_player: Option<Color>,
+    pub moves: Vec<(Color, Vertex)>,
+    pub rules: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub komi: Option<f32>,
+    pub board_x_size: u32,
+    pub board_y_size: u32,
+    /// Turns to analyse, 0 being the position before the first move.
+    /// Only the last position is analysed when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analyze_turns: Option<Vec<usize>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_visits: Option<u32>,
+    /// Seconds between partial results, none are sent when `None`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub report_during_search_every: Option<f32>,
+    pub include_ownership: bool,
+    pub include_moves_ownership: bool,
+    pub include_policy: bool,
+    #[serde(rename = "includePVVisits")]
+    pub include_pv_visits: bool,
+}
+
+impl AnalysisQuery {
+    pub fn new(id: impl Into<String>, size: RectSize) -> Self {
+        Self {
+            id: id.into(),
+            initial_stones: Vec::new(),
+            initial_player: None,
+            moves: Vec::new(),
+            rules: "tromp-taylor".to_string(),
+            komi: None,
+            board_x_size: size.width(),
+            board_y_size: size.height(),
+            analyze_turns: None,
+            max_visits: None,
+            report_during_search_every: None,
+            include_ownership: false,
+            include_moves_ownership: false,
+            include_policy: false,
+            include_pv_visits: false,
+        }
+    }
+
+    /// The position of `game`, with its handicap and setup stones as initial stones.
+    pub fn from_game(id: impl Into<String>, game: &Game) -> Self {
+        let mut query = Self::new(id, game.size());
+        query.initial_stones = game.handicap().iter().map(|v| (Color::Black, *v))
+            .chain(game.setup().iter().map(|m| (m.color, m.vertex)))
+            .collect();
+        query.moves = game.moves().iter().map(|m| (m.color, m.vertex)).collect();
+        query.initial_player = Some(game.moves().first().map(|m| m.color).unwrap_or(game.to_move()));
+        query.komi = Some(game.komi());
+        query
+    }
+
+    pub fn with_moves(mut self, moves: &[Move]) -> Self {
+        self.moves = moves.iter().map(|m| (m.color, m.vertex)).collect();
+        self
+    }
+
+    /// Rules as named by KataGo, e.g. `japanese` or `chinese`.
+    pub fn with_rules(mut self, rules: &str) -> Self {
+        self.rules = rules.to_string();
+        self
+    }
+
+    pub fn with_turns(mut self, turns: Vec<usize>) -> Self {
+        self.analyze_turns = Some(turns);
+        self
+    }
+
+    /// Analyses every position, from the empty board to the last move.
+    pub fn with_all_turns(self) -> Self {
+        let turns = (0..=self.moves.len()).collect();
+        self.with_turns(turns)
+    }
+
+    pub fn with_max_visits(mut self, visits: u32) -> Self {
+        self.max_visits = Some(visits);
+        self
+    }
+
+    pub fn with_ownership(mut self, ownership: bool) -> Self {
+        self.include_ownership = ownership;
+        self
+    }
+
+    /// Ownership after each candidate move, in [`InfoMove::ownership`].
+    pub fn with_moves_ownership(mut self, ownership: bool) -> Self {
+        self.include_moves_ownership = ownership;
+        self
+    }
+
+    pub fn with_policy(mut self, policy: bool) -> Self {
+        self.include_policy = policy;
+        self
+    }
+
+    pub fn with_pv_visits(mut self, pv_visits: bool) -> Self {
+        self.include_pv_visits = pv_visits;
+        self
+    }
+
+    /// Number of final results KataGo sends for this query.
+    pub fn expected_results(&self) -> usize {
+        self.analyze_turns.as_ref().map(|t| t.len()).unwrap_or(1)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RootInfo {
+    pub winrate: f32,
+    pub score_lead: f32,
+    pub score_selfplay: f32,
+    pub score_stdev: f32,
+    pub utility: f32,
+    pub visits: u64,
+    pub current_player: Option<Color>,
+}
+
+/// A move of `moveInfos`, converted to [`InfoMove`].
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MoveInfo {
+    #[serde(rename = "move")]
+    coord: Vertex,
+    visits: u64,
+    winrate: f32,
+    #[serde(default)]
+    score_mean: f32,
+    #[serde(default)]
+    score_stdev: f32,
+    #[serde(default)]
+    score_lead: f32,
+    #[serde(default)]
+    score_selfplay: f32,
+    #[serde(default)]
+    prior: f32,
+    #[serde(default)]
+    utility: f32,
+    #[serde(default)]
+    lcb: f32,
+    #[serde(default)]
+    utility_lcb: f32,
+    #[serde(default)]
+    order: u16,
+    #[serde(default)]
+    pv: Vec<Vertex>,
+    #[serde(default)]
+    pv_visits: Vec<u64>,
+    #[serde(default)]
+    weight: f64,
+    #[serde(default)]
+    ownership: Vec<f32>,
+}
+
+/// Without its ownership, which needs the board size.
+impl From<MoveInfo> for InfoMove {
+    fn from(info: MoveInfo) -> Self {
+        Self {
+            coord: info.coord,
+            visits: info.visits,
+            winrate: info.winrate,
+            score_mean: info.score_mean,
+            score_stdev: info.score_stdev,
+            score_lead: info.score_lead,
+            score_selfplay: info.score_selfplay,
+            prior: info.prior,
+            utility: info.utility,
+            lcb: info.lcb,
+            utility_lcb: info.utility_lcb,
+            order: info.order,
+            pv: info.pv,
+            pv_visits: info.pv_visits,
+            weight: info.weight.round() as u64,
+            ownership: None,
+        }
+    }
+}
+
+/// Fields of a result line, the candidate moves being converted by [`AnalysisResult`].
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawAnalysisResult {
+    id: String,
+    turn_number: usize,
+    #[serde(default)]
+    is_during_search: bool,
+    move_infos: Vec<MoveInfo>,
+    #[serde(default)]
+    root_info: RootInfo,
+    #[serde(default)]
+    ownership: Vec<f32>,
+    #[serde(default)]
+    policy: Vec<f32>,
+}
+
+/// Analysis of one turn of a query.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "RawAnalysisResult")]
+pub struct AnalysisResult {
+    pub id: String,
+    pub turn_number: usize,
+    /// Whether this is a partial result, more will follow for this turn.
+    pub is_during_search: bool,
+    /// Candidate moves, best first. Their ownership is only set once the
+    /// board size is known, see [`with_board_size`](Self::with_board_size).
+    pub move_infos: Vec<InfoMove>,
+    pub root_info: RootInfo,
+    /// Row-major from the top row, from the point of view set by
+    /// `reportAnalysisWinratesAs` in the analysis config.
+    pub ownership: Vec<f32>,
+    /// Row-major from the top row, followed by pass.
+    pub policy: Vec<f32>,
+    moves_ownership: Vec<Vec<f32>>,
+    board_size: Option<RectSize>,
+}
+
+impl From<RawAnalysisResult> for AnalysisResult {
+    fn from(raw: RawAnalysisResult) -> Self {
+        let mut moves_ownership = Vec::with_capacity(raw.move_infos.len());
+        let mut move_infos = Vec::with_capacity(raw.move_infos.len());
+        for mut info in raw.move_infos {
+            moves_ownership.push(std::mem::take(&mut info.ownership));
+            move_infos.push(InfoMove::from(info));
+        }
+        Self {
+            id: raw.id,
+            turn_number: raw.turn_number,
+            is_during_search: raw.is_during_search,
+            move_infos,
+            root_info: raw.root_info,
+            ownership: raw.ownership,
+            policy: raw.policy,
+            moves_ownership,
+            board_size: None,
+        }
+    }
+}
+
+impl AnalysisResult {
+    /// Sets the board size of the query, which results do not repeat, and
+    /// the ownership of the candidate moves. [`AnalysisClient`] does it for
+    /// the queries it sent.
+    pub fn with_board_size(mut self, size: RectSize) -> Self {
+        for (info, values) in self.move_infos.iter_mut().zip(&self.moves_ownership) {
+            info.ownership = OwnershipMap::new(size, values.clone());
+        }
+        self.board_size = Some(size);
+        self
+    }
+
+    pub const fn board_size(&self) -> Option<RectSize> {
+        self.board_size
+    }
+
+    pub fn best_move(&self) -> Option<&InfoMove> {
+        self.move_infos.iter().min_by_key(|m| m.order)
+    }
+
+    /// The result as a `kata-analyze` info line would give it, with values
+    /// still from the engine's point of view; see [`Info::with_perspective`]
+    /// and [`Info::ownership_from`]. Ownership needs the board size.
+    pub fn to_info(&self) -> Info {
+        Info {
+            explored_moves: self.move_infos.clone(),
+            ownership: self.board_size.and_then(|size| OwnershipMap::new(size, self.ownership.clone())),
+        }
+    }
+}
+
+/// A line sent by the analysis engine.
+#[derive(Debug, Clone)]
+pub enum AnalysisMessage {
+    Result(AnalysisResult),
+    /// The query `id` was rejected, or the line could not be read when `id` is `None`.
+    Error { id: Option<String>, field: Option<String>, message: String },
+    /// The query `id` is still analysed despite the problem.
+    Warning { id: Option<String>, field: Option<String>, message: String },
+    /// Acknowledges the termination request `id`.
+    Terminated { id: String, terminate_id: String, turn_numbers: Vec<usize> },
+}
+
+impl AnalysisMessage {
+    /// The query the message is about, the terminated one for `Terminated`.
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            Self::Result(result) => Some(&result.id),
+            Self::Error { id, .. } | Self::Warning { id, .. } => id.as_deref(),
+            Self::Terminated { terminate_id, .. } => Some(terminate_id),
+        }
+    }
+}
+
+impl FromStr for AnalysisMessage {
+    type Err = serde_json::Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Header {
+            id: Option<String>,
+            field: Option<String>,
+            error: Option<String>,
+            warning: Option<String>,
+            action: Option<String>,
+            terminate_id: Option<String>,
+            #[serde(default)]
+            turn_numbers: Vec<usize>,
+        }
+
+        let header: Header = serde_json::from_str(line)?;
+        if let Some(message) = header.error {
+            Ok(Self::Error { id: header.id, field: header.field, message })
+        } else if let Some(message) = header.warning {
+            Ok(Self::Warning { id: header.id, field: header.field, message })
+        } else if header.action.as_deref() == Some("terminate") {
+            Ok(Self::Terminated {
+                id: header.id.unwrap_or_default(),
+                terminate_id: header.terminate_id.unwrap_or_default(),
+                turn_numbers: header.turn_numbers,
+            })
+        } else {
+            serde_json::from_str(line).map(Self::Result)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AnalysisClient {
+    engine: Engine,
+    /// messages read while waiting for another query
+    pending: VecDeque<AnalysisMessage>,
+    next_id: u64,
+    /// board size of the queries sent, which results do not give
+    sizes: HashMap<String, RectSize>,
+}
+
+impl AnalysisClient {
+    /// Starts `program` in analysis mode, e.g. `katago analysis -config ... -model ...`.
+    pub fn start(program: &str, args: &[&str]) -> Result<Self, std::io::Error> {
+        let engine = Engine::new(program, args)?
+            .with_ready_line(Some(KATAGO_ANALYSIS_READY_LINE))
+            .start();
+        Ok(Self::from_engine(engine))
+    }
+
+    /// Uses an already started engine.
+    pub fn from_engine(engine: Engine) -> Self {
+        Self { engine, pending: VecDeque::new(), next_id: 0, sizes: HashMap::new() }
+    }
+
+    /// A fresh query id.
+    pub fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("libgtp-{}", self.next_id)
+    }
+
+    pub fn send(&mut self, query: &AnalysisQuery) -> Result<(), std::io::Error> {
+        self.write_line(&serde_json::to_string(query)?)?;
+        self.sizes.insert(query.id.clone(), RectSize::new(query.board_x_size, query.board_y_size));
+        Ok(())
+    }
+
+    /// Asks to stop analysing the query `terminate_id`. Returns the id of the request.
+    pub fn terminate(&mut self, terminate_id: &str) -> Result<String, std::io::Error> {
+        let id = self.next_id();
+        let line = serde_json::json!({ "id": id, "action": "terminate", "terminateId": terminate_id });
+        self.write_line(&line.to_string())?;
+        Ok(id)
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), std::io::Error> {
+        writeln!(self.engine, "{}", line)?;
+        self.engine.flush()
+    }
+
+    /// The next message, whatever query it is about.
+    pub fn next_message(&mut self) -> Result<AnalysisMessage, std::io::Error> {
+        match self.pending.pop_front() {
+            Some(message) => Ok(message),
+            None => self.read_message(),
+        }
+    }
+
+    /// The next message about the query `id`, keeping the others for later.
+    pub fn next_message_for(&mut self, id: &str) -> Result<AnalysisMessage, std::io::Error> {
+        if let Some(i) = self.pending.iter().position(|m| m.id() == Some(id)) {
+            return Ok(self.pending.remove(i).unwrap());
+        }
+        loop {
+            match self.read_message()? {
+                AnalysisMessage::Error { id: None, message, .. } => return Err(std::io::Error::other(message)),
+                m if m.id() == Some(id) => return Ok(m),
+                m => self.pending.push_back(m),
+            }
+        }
+    }
+
+    /// Waits for the next line sent by the engine, skipping empty ones.
+    fn read_message(&mut self) -> Result<AnalysisMessage, std::io::Error> {
+        loop {
+            let line = self.engine.read_line()?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let message = line.parse()
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, format!("{}: {}", e, line)))?;
+            return Ok(match message {
+                AnalysisMessage::Result(result) => match self.sizes.get(&result.id) {
+                    Some(size) => AnalysisMessage::Result(result.with_board_size(*size)),
+                    None => AnalysisMessage::Result(result),
+                },
+                message => message,
+            });
+        }
+    }
+
+    /// Sends `query` and waits for the final result of every turn, sorted by turn.
+    pub fn analyze(&mut self, query: &AnalysisQuery) -> Result<Vec<AnalysisResult>, std::io::Error> {
+        self.send(query)?;
+        let mut results = Vec::new();
+        while results.len() < query.expected_results() {
+            match self.next_message_for(&query.id)? {
+                AnalysisMessage::Result(result) if !result.is_during_search => results.push(result),
+                AnalysisMessage::Result(_) => {},
+                AnalysisMessage::Error { field, message, .. } => {
+                    let field = field.map(|f| format!(" ({})", f)).unwrap_or_default();
+                    return Err(std::io::Error::other(format!("query {}: {}{}", query.id, message, field)));
+                },
+                AnalysisMessage::Warning { message, .. } => warn!("query {}: {}", query.id, message),
+                AnalysisMessage::Terminated { .. } => break,
+            }
+        }
+        self.sizes.remove(&query.id);
+        results.sort_by_key(|r| r.turn_number);
+        Ok(results)
+    }
+
+    /// Analyses every position of `game` with `max_visits` each.
+    pub fn analyze_game(&mut self, game: &Game, max_visits: u32) -> Result<Vec<AnalysisResult>, std::io::Error> {
+        let query = AnalysisQuery::from_game(self.next_id(), game)
+            .with_all_turns()
+            .with_max_visits(max_visits);
+        self.analyze(&query)
+    }
+}
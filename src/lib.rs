@@ -1,12 +1,24 @@
+#![cfg_attr(not(any(feature = "controller", test)), no_std)]
 extern crate alloc;
 
 pub mod model;
+pub mod sgf;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 pub use model::Answer;
 pub use model::Info;
 #[cfg(feature = "controller")]
 pub mod controller;
 #[cfg(feature = "controller")]
 pub mod engine;
+#[cfg(feature = "kata-analysis")]
+pub mod kata_analysis;
+#[cfg(feature = "controller")]
+pub mod mock;
+#[cfg(feature = "review")]
+pub mod review;
+#[cfg(feature = "controller")]
+pub mod transcript;
 #[cfg(feature = "controller")]
 pub use engine::Engine;
 #[cfg(feature = "controller")]
@@ -29,7 +41,6 @@ pub mod prelude {
 
 #[test]
 fn test_collection_macro() {
-    use alloc::vec::Vec;
     use alloc::format;
     use core::str::FromStr;
 
@@ -63,6 +74,63 @@ fn test_command() {
     assert_eq!(command.to_string(), String::from("kata-analyze interval 100\n"));
 }
 
+#[test]
+fn test_command_reader() {
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+    use crate::model::CommandReader;
+
+    let input = "# a comment\r\n\n   \t\n1 boardsize\t19 # trailing\r\nkomi 6.5\x07\nname";
+    let commands: Vec<_> = CommandReader::new(input.bytes()).collect();
+    assert_eq!(3, commands.len());
+    assert_eq!("1 boardsize 19\n", commands[0].as_ref().unwrap().to_string());
+    assert_eq!("komi 6.5\n", commands[1].as_ref().unwrap().to_string());
+    assert_eq!("name\n", commands[2].as_ref().unwrap().to_string());
+
+    assert!("".parse::<crate::model::Command>().is_err());
+    assert!("  # only a comment".parse::<crate::model::Command>().is_err());
+    assert!("42".parse::<crate::model::Command>().is_err());
+}
+
+#[test]
+fn test_gtp_command() {
+    use alloc::string::ToString;
+    use core::convert::TryFrom;
+    use crate::model::*;
+
+    let command: GtpCommand = "time_settings 300 30 5".parse().unwrap();
+    assert_eq!(GtpCommand::TimeSettings { main: 300, byo_yomi: 30, stones: 5 }, command);
+    assert_eq!("time_settings 300 30 5\n", command.to_string());
+
+    let command: GtpCommand = "time_left W 120 3".parse().unwrap();
+    assert_eq!(GtpCommand::TimeLeft { color: Color::White, time: 120, stones: 3 }, command);
+    assert_eq!("time_left W 120 3\n", command.to_string());
+
+    let command: GtpCommand = "loadsgf game.sgf 42".parse().unwrap();
+    assert_eq!(GtpCommand::Loadsgf { path: "game.sgf".to_string(), move_number: Some(42) }, command);
+
+    let command: GtpCommand = "kata-analyze B interval 50 ownership true".parse().unwrap();
+    assert_eq!("kata-analyze B interval 50 ownership true\n", command.to_string());
+    let command: GtpCommand = "kata-analyze B 50".parse().unwrap();
+    assert_eq!("kata-analyze B 50\n", command.to_string());
+    assert_eq!(GtpCommand::Name, "2 name".parse().unwrap());
+
+    assert!("komi D4".parse::<GtpCommand>().is_err());
+    assert!("play B".parse::<GtpCommand>().is_err());
+    assert!("boardsize 19 19".parse::<GtpCommand>().is_err());
+
+    for wire in ["play W Q16", "genmove b", "set_position B D4 W Q16", "rectangular_boardsize 9 13",
+                 "final_status_list dead", "kata-set-param maxVisits 100", "kata-get-rules",
+                 "set_free_handicap D4 Q16 K10"] {
+        let command: Command = wire.parse().unwrap();
+        let typed = GtpCommand::try_from(&command).unwrap();
+        assert_eq!(command.to_string(), Command::from(&typed).to_string());
+        assert_eq!(typed, GtpCommand::try_from(Command::from(&typed)).unwrap());
+    }
+
+    assert_eq!("3 undo\n", GtpCommand::Undo.with_id(3).to_string());
+}
+
 #[test]
 fn test_response() {
     use crate::prelude::*;
@@ -82,6 +150,435 @@ fn test_response() {
     assert_eq!("=\n\n", response.to_string());
 }
 
+#[test]
+fn test_response_parse_for() {
+    use crate::model::*;
+    use alloc::string::ToString;
+
+    let response = Response::parse_for(CommandName::Name, "= 5\n\n").unwrap();
+    assert!(response.data().as_ref().unwrap().is_name());
+
+    let response = Response::parse_for(CommandName::Genmove, "=3 pass\n\n").unwrap();
+    assert_eq!(&Some(3), response.id());
+    assert_eq!(Some(&Vertex::Pass), response.data().as_ref().unwrap().as_vertex());
+
+    let response = Response::parse_for(CommandName::Play, "=\n\n").unwrap();
+    assert!(response.data().is_none());
+
+    let response = Response::parse_for(CommandName::GetKomi, "= 6.5\n\n").unwrap();
+    assert_eq!(Some(&6.5), response.data().as_ref().unwrap().as_float());
+
+    let response = Response::parse_for(CommandName::ListCommands, "= name\nplay\nlz-setoption\n\n").unwrap();
+    assert_eq!(3, response.data().as_ref().unwrap().as_command_names().unwrap().len());
+
+    let response = Response::parse_for(CommandName::FinalStatusList, "= A1 B2\nC3\n\n").unwrap();
+    assert_eq!(2, response.data().as_ref().unwrap().as_vertex_lists().unwrap().len());
+
+    let response = Response::parse_for(CommandName::KataGenmoveAnalyze, "=\nplay D4\n\n").unwrap();
+    assert_eq!("D4", response.data().as_ref().unwrap().to_string());
+
+    assert!(Response::parse_for(CommandName::ProtocolVersion, "= two\n\n").is_err());
+    assert!(Answer::parse_for(CommandName::Play, "? illegal move\n\n").unwrap().is_failure());
+}
+
+#[test]
+fn test_parse_error() {
+    use crate::model::*;
+    use alloc::string::ToString;
+    use core::error::Error;
+
+    let err = "boardsize nineteen".parse::<Command>().unwrap_err();
+    assert_eq!(ParseErrorKind::WrongArgs, err.kind());
+    assert_eq!(Some(10), err.offset());
+    assert!(err.source().is_some());
+
+    let err = "3 foo bar".parse::<Command>().unwrap_err();
+    assert_eq!(ParseErrorKind::WrongCommandName, err.kind());
+    assert_eq!(Some(2), err.offset());
+
+    let err = "info move D4 visits 12 winrate abc".parse::<Info>().unwrap_err();
+    assert_eq!(ParseErrorKind::WrongArgs, err.kind());
+    assert_eq!(Some("abc"), err.input());
+    assert_eq!(Some(31), err.offset());
+    assert_eq!(Some("a number"), err.expected_what());
+    assert!(matches!(err.cause(), Some(ParseErrorCause::Float(_))));
+
+    let err = "info move D4 visits 12 pv D4 Z99".parse::<Info>().unwrap_err();
+    assert_eq!(Some("Z99"), err.input());
+    assert_eq!(Some(29), err.offset());
+
+    let err = "Z99".parse::<Vertex>().unwrap_err();
+    assert_eq!(err, ParseErrorKind::WrongCoordinates);
+    assert_eq!("invalid coordinates, expected a vertex like D4, pass or resign, found \"Z99\"", err.to_string());
+}
+
+#[test]
+fn test_parsers_do_not_panic() {
+    use crate::model::*;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+
+    let inputs = ["", "?", "=", "=\n", "= a\nb", "0", "play", "play B", "genmove", "W", "B+",
+                  "W+R+3", "\u{e9}4", " ", "#", "@1", "info", "info move", "info move D4 visits",
+                  "info pv", "info move D4 ownership 0.1 x", "ownership ", "kata-list-params",
+                  "set_position B", "komi", "1", "\x00\x01"];
+    let tokens = ["(;", "[", "]", ")", "SZ[", "info", "move", "D4", "pv", "pvVisits", "=", "?", "1", "-1", "B", "W", "pass",
+                  "\n", " ", "#", "ownership", "0.5", "play", "genmove", "+", "Z", "\u{e9}"];
+    let parse_all = |s: &str| {
+        let _ = s.parse::<Command>();
+        let _ = s.parse::<GtpCommand>();
+        let _ = Answer::parse_answer(s);
+        let _ = Answer::parse_for(CommandName::ListCommands, s);
+        let _ = Answer::parse_for(CommandName::FinalStatusList, s);
+        let _ = s.parse::<Info>();
+        let _ = s.parse::<Vertex>();
+        let _ = s.parse::<Move>();
+        let _ = s.parse::<Score>();
+        let _ = s.parse::<Collection>();
+        let _ = s.parse::<crate::sgf::GameTree>().map(|t| t.to_game());
+        let _ = CommandReader::new(s.bytes()).count();
+    };
+
+    inputs.iter().for_each(|s| parse_all(s));
+
+    let mut seed: u32 = 0x9e37_79b9;
+    for _ in 0..2000 {
+        let mut s = String::new();
+        for _ in 0..(seed % 8) {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            s.push_str(tokens[seed as usize % tokens.len()]);
+        }
+        parse_all(&s);
+        parse_all(&s.split_ascii_whitespace().collect::<Vec<_>>().join(" "));
+    }
+}
+
+#[test]
+fn test_vertex_board_size() {
+    use crate::model::*;
+    use alloc::string::ToString;
+
+    let v: Vertex = "z25".parse().unwrap();
+    assert_eq!(Vertex::Coord(25, 25), v);
+    assert_eq!("Z25", v.to_string());
+    assert_eq!(Vertex::Coord(8, 1), "H1".parse::<Vertex>().unwrap());
+    assert_eq!(Vertex::Coord(9, 1), "J1".parse::<Vertex>().unwrap());
+    assert!("I5".parse::<Vertex>().is_err());
+    assert!("A0".parse::<Vertex>().is_err());
+    assert!("A26".parse::<Vertex>().is_err());
+    for wrong in ["D+4", "D-4", "D 4", "D"] {
+        assert!(wrong.parse::<Vertex>().is_err(), "{:?}", wrong);
+    }
+
+    let nineteen = RectSize::square(19);
+    assert!(Vertex::parse_for("T19", nineteen).is_ok());
+    assert!(Vertex::parse_for("U1", nineteen).is_err());
+    assert!(Vertex::parse_for("pass", nineteen).is_ok());
+
+    let rect = RectSize::new(9, 13);
+    assert!(Vertex::parse_for("J13", rect).is_ok());
+    assert!(Vertex::parse_for("K1", rect).is_err());
+
+    assert_eq!(Some((0, 0)), "A19".parse::<Vertex>().unwrap().to_row_col(nineteen));
+    assert_eq!(Some((18, 18)), "T1".parse::<Vertex>().unwrap().to_row_col(nineteen));
+    assert_eq!(Some(19 * 15 + 3), "D4".parse::<Vertex>().unwrap().to_index(nineteen));
+    for (i, v) in rect.vertices().enumerate() {
+        assert_eq!(Some(i), v.to_index(rect));
+        assert_eq!(Some(v), Vertex::from_index(i, rect));
+    }
+    assert_eq!(9 * 13, rect.vertices().count());
+    assert!(!RectSize::square(26).is_valid());
+    assert_eq!(None, Vertex::from_row_col(0, 25, RectSize::new(300, 1)));
+    assert_eq!(None, Vertex::from_row_col(0, 0, RectSize::new(1, 300)));
+    assert_eq!(None, Vertex::from_row_col(299, 0, RectSize::new(1, 300)));
+    assert_eq!(Some("A1".parse().unwrap()), Vertex::from_row_col(24, 0, RectSize::new(1, 25)));
+    assert_eq!(None, Vertex::from_index(300, RectSize::new(300, 1)));
+}
+
+#[test]
+fn test_board() {
+    use crate::model::*;
+    use alloc::vec::Vec;
+
+    let mov = |s: &str| s.parse::<Move>().unwrap();
+    let vertex = |s: &str| s.parse::<Vertex>().unwrap();
+    let setup = |rule: KoRule| {
+        let mut board = Board::square(5).with_ko_rule(rule);
+        for v in ["B4", "A3", "B2"] {
+            board.set(vertex(v), Some(Color::Black), Color::Black).unwrap();
+        }
+        for v in ["C4", "B3", "D3", "C2"] {
+            board.set(vertex(v), Some(Color::White), Color::Black).unwrap();
+        }
+        board
+    };
+
+    let mut board = setup(KoRule::Simple);
+    assert_eq!(vec![vertex("B3")], board.play(mov("B C3")).unwrap());
+    assert_eq!(None, board.get(vertex("B3")));
+    assert_eq!(Some(vertex("B3")), board.ko(Color::White));
+    assert_eq!(Err(IllegalMove::Ko), board.play(mov("W B3")));
+    assert_eq!(Err(IllegalMove::Occupied), board.play(mov("W C3")));
+    assert_eq!(Err(IllegalMove::OffBoard), board.play(mov("W F1")));
+    board.play(mov("W pass")).unwrap();
+    board.play(mov("B pass")).unwrap();
+    assert_eq!(1, board.play(mov("W B3")).unwrap().len());
+
+    let mut board = setup(KoRule::PositionalSuperko);
+    board.play(mov("B C3")).unwrap();
+    board.play(mov("W pass")).unwrap();
+    board.play(mov("B pass")).unwrap();
+    assert_eq!(Err(IllegalMove::Superko), board.play(mov("W B3")));
+
+    // the recapture repeats the setup position, but with Black to move instead of White
+    for (rule, recapture) in [(KoRule::PositionalSuperko, Err(IllegalMove::Superko)),
+                              (KoRule::SituationalSuperko, Ok(vec![vertex("C3")]))] {
+        let mut board = setup(rule);
+        board.set(vertex("C2"), Some(Color::White), Color::White).unwrap();
+        board.play(mov("B C3")).unwrap();
+        board.play(mov("B pass")).unwrap();
+        assert_eq!(recapture, board.play(mov("W B3")), "{:?}", rule);
+    }
+
+    let mut board = Board::new(RectSize::new(3, 2));
+    board.play(mov("W A2")).unwrap();
+    board.play(mov("W B1")).unwrap();
+    assert_eq!(Err(IllegalMove::Suicide), board.check(mov("B A1")));
+    assert!(board.is_legal(mov("W A1")));
+    board.play(mov("B C1")).unwrap();
+    assert_eq!(2, board.group_at(vertex("A2")).len() + board.group_at(vertex("B1")).len());
+    assert_eq!(2, board.liberties_at(vertex("B1")));
+    assert_eq!(vec![vertex("C1")], board.stones(Color::Black).collect::<Vec<_>>());
+    assert_eq!("illegal move: suicide", IllegalMove::Suicide.to_failure().message());
+    assert_eq!("illegal move: ko", IllegalMove::Ko.to_failure().message());
+    assert!(std::panic::catch_unwind(|| Board::new(RectSize::new(26, 1))).is_err());
+    assert_eq!(Err(InvalidBoardSize(RectSize::new(0, 9))), Board::try_new(RectSize::new(0, 9)).map(|_| ()));
+    assert!(Game::try_new(RectSize::square(29)).is_err());
+}
+
+#[test]
+fn test_game() {
+    use crate::model::*;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    let handicap = |n, size| fixed_handicap_vertices(n, RectSize::square(size))
+        .map(|l| l.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" "));
+    assert_eq!(Some("D4 Q16 D16".to_string()), handicap(3, 19));
+    assert_eq!(Some("D4 Q16 D16 Q4 D10 Q10 K10".to_string()), handicap(7, 19));
+    assert_eq!(Some("D4 Q16 D16 Q4 D10 Q10 K4 K16 K10".to_string()), handicap(9, 19));
+    assert_eq!(Some("C3 G7 C7 G3 E5".to_string()), handicap(5, 9));
+    assert_eq!(None, handicap(5, 8));
+    assert_eq!(None, handicap(1, 19));
+
+    let mut game = Game::new(RectSize::square(9)).with_komi(0.5);
+    game.place_fixed_handicap(2).unwrap();
+    assert_eq!(Color::White, game.to_move());
+    assert_eq!(Err(HandicapError::BoardNotEmpty), game.place_fixed_handicap(2));
+
+    for m in ["W E5", "B D5", "W pass", "B pass"] {
+        game.play(m.parse().unwrap()).unwrap();
+    }
+    assert!(game.is_over());
+    assert_eq!(4, game.move_number());
+    assert_eq!(Some("B PASS".parse().unwrap()), game.undo());
+    assert!(!game.is_over());
+    assert_eq!(Color::Black, game.to_move());
+
+    let commands: Vec<_> = game.to_commands().iter().map(|c| c.to_string()).collect();
+    assert_eq!(vec!["boardsize 9\n", "clear_board\n", "komi 0.5\n", "set_free_handicap C3 G7\n",
+                    "play W E5\n", "play B D5\n", "play W PASS\n"], commands);
+
+    let mut game = Game::new(RectSize::square(5));
+    for m in ["B A2", "W B2", "B B1", "W C1"] {
+        game.play(m.parse().unwrap()).unwrap();
+    }
+    game.play("W A1".parse().unwrap()).unwrap();
+    assert_eq!(1, game.captures(Color::White));
+    game.undo();
+    assert_eq!(0, game.captures(Color::White));
+    assert_eq!(Some(Color::Black), game.board().get("B1".parse().unwrap()));
+}
+
+#[test]
+fn test_game_apply() {
+    use crate::model::*;
+
+    let mut game = Game::new(RectSize::square(19));
+    let apply = |game: &mut Game, command: &str, response: &str| {
+        let command: GtpCommand = command.parse().unwrap();
+        let response = Response::parse_for(command.name(), response).unwrap();
+        game.apply(&command, response.data().as_ref())
+    };
+
+    assert!(apply(&mut game, "boardsize 9", "="));
+    assert_eq!(RectSize::square(9), game.size());
+    assert!(apply(&mut game, "komi 7.5", "="));
+    assert!(apply(&mut game, "fixed_handicap 2", "= C3 G7"));
+    assert_eq!(Color::White, game.to_move());
+    assert!(apply(&mut game, "genmove W", "= E5"));
+    assert!(apply(&mut game, "play B D5", "="));
+    assert_eq!(Some(Color::White), game.board().get("E5".parse().unwrap()));
+    assert!(apply(&mut game, "undo", "="));
+    assert_eq!(1, game.move_number());
+    assert!(!apply(&mut game, "play W E5", "="));
+    assert!(apply(&mut game, "clear_board", "="));
+    assert!(game.board().is_empty());
+    assert_eq!(7.5, game.komi());
+    assert!(!apply(&mut game, "loadsgf game.sgf", "="));
+    assert!(!apply(&mut game, "boardsize 29", "="));
+    assert!(!apply(&mut game, "rectangular_boardsize 9 0", "="));
+    assert_eq!(RectSize::square(9), game.size());
+}
+
+#[test]
+fn test_scoring() {
+    use crate::model::*;
+    use alloc::string::ToString;
+
+    assert_eq!(Score::Draw, "0".parse().unwrap());
+    assert_eq!(Score::Win { color: Color::White, margin: None, reason: WinReason::Resign }, "W+R".parse().unwrap());
+    assert_eq!(Score::Win { color: Color::Black, margin: None, reason: WinReason::Time }, "B+T".parse().unwrap());
+    let score: Score = "B+7.5".parse().unwrap();
+    assert_eq!(Some(Color::Black), score.winner());
+    assert_eq!(Some(7.5), score.margin());
+    assert_eq!("B+7.5", score.to_string());
+    assert_eq!("W+R", "W+R".parse::<Score>().unwrap().to_string());
+    for wrong in ["", "B7", "X+1", "W+-1", "B+nan", "W+Res"] {
+        assert!(wrong.parse::<Score>().is_err(), "{:?}", wrong);
+    }
+
+    let mut game = Game::new(RectSize::square(5)).with_komi(0.5);
+    for m in ["B B1", "W C1", "B B2", "W C2", "B B3", "W C3", "B B4", "W C4", "B B5", "W C5", "B E3", "W pass"] {
+        game.play(m.parse().unwrap()).unwrap();
+    }
+    let dead = ["E3".parse().unwrap()];
+
+    let area = game.score(ScoringRules::area(0.0), &dead);
+    assert_eq!([5, 10], area.territory);
+    assert_eq!("W+5.5", area.score().to_string());
+    let territory = game.score(ScoringRules::territory(0.0), &dead);
+    assert_eq!([0, 1], territory.prisoners);
+    assert_eq!("W+6.5", territory.score().to_string());
+    let undecided = game.score(ScoringRules::area(0.0), &[]);
+    assert_eq!(9, undecided.dame);
+    assert_eq!("B+5.5", undecided.score().to_string());
+
+    let rules = ScoringRules::area(7.0).with_handicap_compensation(HandicapCompensation::NMinusOne);
+    let detail = rules.score(&Board::square(9), &[], [0, 0], 4);
+    assert_eq!(Score::from_difference(-10.0), detail.score());
+
+    // each group has one eye and shares C1 with the other
+    let mut seki = Board::new(RectSize::new(5, 2));
+    for (vertex, color) in [("A1", Color::Black), ("B1", Color::Black), ("B2", Color::Black), ("C2", Color::Black),
+                            ("D2", Color::White), ("D1", Color::White), ("E1", Color::White)] {
+        seki.set(vertex.parse().unwrap(), Some(color), Color::Black).unwrap();
+    }
+    let area = ScoringRules::area(0.0).score(&seki, &[], [0, 0], 0);
+    assert_eq!(([1, 1], 1), (area.territory, area.dame));
+    let territory = ScoringRules::territory(0.0).score(&seki, &[], [0, 0], 0);
+    assert_eq!(([0, 0], 3), (territory.territory, territory.dame));
+    assert_eq!(Score::Draw, territory.score());
+}
+
+#[test]
+fn test_score() {
+    use crate::model::*;
+    use alloc::format;
+    use alloc::string::ToString;
+
+    for (wire, short, long) in [("0", "0", "Draw"), ("Draw", "0", "Draw"), ("Void", "Void", "Void"),
+                                ("B+12.5", "B+12.5", "B+12.5"), ("W+", "W+", "W+"),
+                                ("W+R", "W+R", "W+Resign"), ("B+Resign", "B+R", "B+Resign"),
+                                ("B+T", "B+T", "B+Time"), ("W+Time", "W+T", "W+Time"),
+                                ("W+F", "W+F", "W+Forfeit"), ("B+Forfeit", "B+F", "B+Forfeit")] {
+        let score: Score = wire.parse().unwrap();
+        assert_eq!(short, score.to_string());
+        assert_eq!(long, format!("{:#}", score));
+        assert_eq!(score, short.parse().unwrap());
+        assert_eq!(score, long.parse().unwrap());
+    }
+
+    let score: Score = "W+3.5".parse().unwrap();
+    assert_eq!(Some(-3.5), score.margin_for(Color::Black));
+    assert_eq!(Some(3.5), score.margin_for(Color::White));
+    assert_eq!(Some(0.0), score.outcome_for(Color::Black));
+    assert_eq!(Some(WinReason::Score), score.reason());
+    assert_eq!(Some(0.5), Score::Draw.outcome_for(Color::White));
+    assert_eq!(None, Score::Void.outcome_for(Color::White));
+    assert_eq!(None, "B+R".parse::<Score>().unwrap().margin_for(Color::Black));
+}
+
+#[test]
+fn test_sgf() {
+    use crate::model::*;
+    use crate::sgf::*;
+    use alloc::string::ToString;
+
+    let input = "(;FF[4]GM[1]SZ[9]KM[6.5]HA[2]AB[cc][gg]RE[W+Resign]RU[Japanese]\nC[a \\] comment\\\nwrapped]\n\
+                 ;W[ee];B[]\n(;W[dc])\n(;W[cd]))";
+    let tree: GameTree = input.parse().unwrap();
+    assert_eq!(RectSize::square(9), tree.size().unwrap());
+    assert_eq!(6.5, tree.komi().unwrap());
+    assert_eq!(2, tree.handicap().unwrap());
+    assert_eq!(Some("W+R".parse().unwrap()), tree.result().unwrap());
+    assert_eq!(None, "(;RE[?])".parse::<GameTree>().unwrap().result().unwrap());
+    assert!("(;RE[B+?])".parse::<GameTree>().unwrap().result().is_err());
+    assert_eq!(Some("Japanese"), tree.rules());
+    assert_eq!(Some("a ] commentwrapped"), tree.root().unwrap().get("C"));
+    assert_eq!(4, tree.main_line().count());
+    assert_eq!("W E5 B PASS W D7", tree.moves().unwrap().to_string());
+    assert_eq!(tree, tree.to_string().parse().unwrap());
+
+    let game = tree.to_game().unwrap();
+    assert_eq!(&["C7".parse::<Vertex>().unwrap(), "G3".parse().unwrap()], game.handicap());
+    assert_eq!(Some(Color::White), game.board().get("D7".parse().unwrap()));
+    assert_eq!(Color::Black, game.to_move());
+
+    let written = GameTree::from_game(&game);
+    assert!(written.to_string().starts_with("(;FF[4]GM[1]CA[UTF-8]SZ[9]KM[6.5]HA[2]AB[cc][gg];W[ee];B[];W[dc])"));
+    assert_eq!(game.board(), written.to_game().unwrap().board());
+
+    let tree: GameTree = "(;SZ[13:9]AB[aa:bb]AW[mi]PL[W])".parse().unwrap();
+    assert_eq!(RectSize::new(13, 9), tree.size().unwrap());
+    let game = tree.to_game().unwrap();
+    assert_eq!(5, game.setup().len());
+    assert_eq!(Some(Color::White), game.board().get("N1".parse().unwrap()));
+    assert_eq!(Color::White, game.to_move());
+    assert!(GameTree::from_game(&game).to_string().contains("SZ[13:9]"));
+
+    let moves: List<Move> = ["B D4", "W Q16", "B pass"].iter().map(|m| m.parse().unwrap()).collect();
+    let tree = GameTree::from_moves(RectSize::square(19), 7.5, &moves);
+    assert_eq!(moves, tree.moves().unwrap());
+    assert_eq!(2, parse_collection("(;B[dd])(;W[pp])").unwrap().len());
+
+    for wrong in ["", "x", "(;B[dd]", "(;B[dd)", "(;B)", "()", "(;B[dd](;W[pp]);B[aa])"] {
+        assert!(wrong.parse::<GameTree>().is_err(), "{:?}", wrong);
+    }
+    assert!("(;B[zz])".parse::<GameTree>().unwrap().moves().is_err());
+    assert!("(;B[dd];W[dd])".parse::<GameTree>().unwrap().to_game().is_err());
+
+    let nested = |depth: usize| "(;".repeat(depth) + &")".repeat(depth);
+    let deepest: GameTree = nested(MAX_NESTING).parse().unwrap();
+    assert_eq!(deepest, deepest.to_string().parse().unwrap());
+    assert!(parse_collection(&nested(20000)).is_err());
+}
+
+#[test]
+fn test_sgf_commands() {
+    use crate::sgf::GameTree;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    let game = "(;SZ[9]KM[7]AB[cc]AW[gg];B[ee])".parse::<GameTree>().unwrap().to_game().unwrap();
+    let commands: Vec<_> = game.to_commands_with_set_position().iter().map(|c| c.to_string()).collect();
+    assert_eq!(vec!["boardsize 9\n", "clear_board\n", "komi 7\n", "set_position B C7 W G3\n", "play B E5\n"], commands);
+    let commands: Vec<_> = game.to_commands().iter().map(|c| c.to_string()).collect();
+    assert_eq!(vec!["boardsize 9\n", "clear_board\n", "komi 7\n", "play B C7\n", "play W G3\n", "play B E5\n"], commands);
+}
+
 #[test]
 fn test_failure() {
     use crate::model::Failure;
@@ -91,10 +588,248 @@ fn test_failure() {
     assert_eq!(failure.to_string(), "? syntax error\n\n");
 }
 
+
+#[cfg(feature = "serde")]
 #[test]
-fn test_controller() {
-    use crate::Controller;
-    
-    let _controller = Controller::new("../Ainalyzer/KataGo/katago", &["gtp"]);
-    log::info!("hello");
+fn test_serde() {
+    use crate::model::{Board, Color, Command, Game, Info, List, Move, OwnershipMap, RectSize, Score, Vertex};
+    use crate::sgf::GameTree;
+    use alloc::string::ToString;
+
+    assert_eq!("\"D4\"", serde_json::to_string(&"D4".parse::<Vertex>().unwrap()).unwrap());
+    assert_eq!("\"PASS\"", serde_json::to_string(&Vertex::Pass).unwrap());
+    assert_eq!("\"W\"", serde_json::to_string(&Color::White).unwrap());
+    assert_eq!(Color::Black, serde_json::from_str::<Color>("\"black\"").unwrap());
+    assert!(serde_json::from_str::<Vertex>("\"Z99\"").is_err());
+
+    let moves: List<Move> = ["B D4", "W Q16", "B pass"].iter().map(|m| m.parse().unwrap()).collect();
+    let json = serde_json::to_string(&moves).unwrap();
+    assert_eq!("[\"B D4\",\"W Q16\",\"B PASS\"]", json);
+    assert_eq!(moves, serde_json::from_str::<List<Move>>(&json).unwrap());
+
+    let score: Score = "W+R".parse().unwrap();
+    assert_eq!("\"W+R\"", serde_json::to_string(&score).unwrap());
+    assert_eq!(score, serde_json::from_str::<Score>("\"W+Resign\"").unwrap());
+
+    let info: Info = "info move D4 visits 10 winrate 0.5 pv D4 Q16 info move Q16 visits 2 winrate 0.4 pv Q16 ownership 0.5 -0.5".parse().unwrap();
+    let json = serde_json::to_string(&info).unwrap();
+    assert!(json.contains("\"pv\":[\"D4\",\"Q16\"]"), "{}", json);
+    let info: Info = serde_json::from_str(&json).unwrap();
+    assert_eq!(2, info.explored_moves.len());
+    assert_eq!(&[0.5, -0.5], info.ownership.unwrap().values());
+
+    let map = OwnershipMap::new(RectSize::square(2), vec![0.5, -0.5, 0.5, -0.5]).unwrap();
+    let json = serde_json::to_string(&map).unwrap();
+    assert_eq!(map, serde_json::from_str(&json).unwrap());
+    assert!(json.contains("\"values\":[0.5,-0.5,0.5,-0.5]"), "{}", json);
+    let truncated = json.replace("[0.5,-0.5,0.5,-0.5]", "[0.1]");
+    assert!(serde_json::from_str::<OwnershipMap>(&truncated).is_err());
+
+    let command: Command = "3 play W Q16".parse().unwrap();
+    let command: Command = serde_json::from_str(&serde_json::to_string(&command).unwrap()).unwrap();
+    assert_eq!("3 play W Q16\n", command.to_string());
+
+    let mut game = Game::new(RectSize::square(9)).with_komi(6.5);
+    game.play("B E5".parse().unwrap()).unwrap();
+    let game: Game = serde_json::from_str(&serde_json::to_string(&game).unwrap()).unwrap();
+    assert_eq!(Some(Color::Black), game.board().get("E5".parse().unwrap()));
+
+    let board = game.board();
+    let mut json = serde_json::to_value(board).unwrap();
+    json["hash"] = 1.into();
+    assert_eq!(board, &serde_json::from_value::<Board>(json.clone()).unwrap());
+    let mut wrong_size = json.clone();
+    wrong_size["size"] = serde_json::json!([19, 19]);
+    assert!(serde_json::from_value::<Board>(wrong_size).is_err());
+    json["ko"] = serde_json::json!(["K10", "W"]);
+    assert!(serde_json::from_value::<Board>(json).is_err());
+
+    let tree: GameTree = "(;SZ[9]KM[7];B[ee])".parse().unwrap();
+    assert_eq!(tree, serde_json::from_str(&serde_json::to_string(&tree).unwrap()).unwrap());
+}
+
+#[test]
+fn test_answer_display() {
+    use crate::model::{Answer, CommandName, Info};
+    use alloc::string::ToString;
+
+    let line = "info move D4 visits 10 utility 0.1 winrate 0.55 scoreMean 1.5 scoreStdev 20 scoreLead 1.5 scoreSelfplay 2 prior 0.3 lcb 0.5 utilityLcb 0.05 weight 10 order 0 pv D4 Q16 pvVisits 10 4 info move Q16 visits 2 winrate 0.4 order 1 pv Q16 ownership 0.25 -0.5";
+    let info: Info = line.parse().unwrap();
+    let again: Info = info.to_string().parse().unwrap();
+    assert_eq!(info.to_string(), again.to_string());
+    assert!(info.to_string().starts_with("info move D4 visits 10 utility 0.1 winrate 0.55"));
+    assert!(info.to_string().ends_with("pv Q16 ownership 0.25 -0.5"));
+    assert_eq!(vec![10, 4], again.explored_moves[0].pv_visits);
+    assert_eq!(&[0.25, -0.5], again.ownership.unwrap().values());
+
+    assert_eq!("=3 D4\n\n", Answer::parse_for(CommandName::Genmove, "=3 D4\n\n").unwrap().to_string());
+    assert_eq!("? unknown command\n\n", Answer::parse_answer("? unknown command\n\n").unwrap().to_string());
+    assert!(Answer::parse_answer(line).unwrap().to_string().starts_with("info move D4"));
+    assert!(Answer::parse_answer("garbage").is_err());
+    assert!("move D4 visits 3".parse::<Info>().is_err());
+}
+
+#[test]
+fn test_ownership() {
+    use crate::model::{Board, Color, Info, OwnershipMap, RectSize, Vertex};
+
+    // 3x3 board, rows from the top: C3 is the first value, A1 the seventh
+    let values = vec![0.9, 0.8, -0.2,
+                      0.7, 0.1, -0.6,
+                      0.95, -0.4, -0.9];
+    let map = OwnershipMap::from_values(values.clone()).unwrap();
+    assert_eq!(RectSize::square(3), map.size());
+    let v = |s: &str| s.parse::<Vertex>().unwrap();
+    assert_eq!(Some(0.9), map.get(v("A3")));
+    assert_eq!(Some(0.95), map.get(v("A1")));
+    assert_eq!(Some(-0.9), map.get(v("C1")));
+    assert_eq!(Some(0.9), map.get_for(v("C1"), Color::White));
+    assert_eq!(None, map.get(v("D4")));
+    assert_eq!(Some(Color::White), map.owner(v("C2"), 0.5));
+    assert_eq!(None, map.owner(v("B2"), 0.5));
+    assert!((map.area(Color::Black) - 3.45).abs() < 1e-5);
+    assert!((map.area(Color::White) - 2.1).abs() < 1e-5);
+    assert_eq!(vec![Some(Color::Black), Some(Color::Black), None,
+                    Some(Color::Black), None, Some(Color::White),
+                    Some(Color::Black), None, Some(Color::White)], map.territory_map(0.5));
+
+    let mut board = Board::square(3);
+    board.set(v("A3"), Some(Color::Black), Color::White).unwrap();
+    board.set(v("C1"), Some(Color::Black), Color::White).unwrap();
+    board.set(v("B1"), Some(Color::White), Color::Black).unwrap();
+    assert_eq!(vec![v("C1")], map.dead_stones(&board, 0.5));
+    // own stones are not territory, dead ones count for the opponent
+    assert!((map.territory(Color::Black, &board) - 2.55).abs() < 1e-5);
+    assert!((map.territory(Color::White, &board) - 1.7).abs() < 1e-5);
+
+    assert!(OwnershipMap::new(RectSize::new(3, 2), values.clone()).is_none());
+    let map = OwnershipMap::from_values(values[..6].to_vec()).unwrap();
+    assert_eq!(RectSize::new(6, 1), map.size());
+    assert_eq!(RectSize::new(19, 13), OwnershipMap::from_values(vec![0.0; 19 * 13]).unwrap().size());
+    // no board is 29 points wide
+    assert!(OwnershipMap::from_values(vec![0.0; 29]).is_none());
+    assert!(OwnershipMap::from_values(Vec::new()).is_none());
+    let map = map.with_size(RectSize::new(3, 2)).unwrap();
+    assert_eq!(Some(0.7), map.get(v("A1")));
+    assert!(map.with_size(RectSize::square(3)).is_err());
+
+    let info: Info = "info move A1 visits 3 pv A1 B1 movesOwnership 0.1 0.2 0.3 0.4 0.5 0.6 info move B2 visits 1 pv B2 pvVisits 1 ownership 1 -1 0 0 0 0 ownershipStdev 0 0 0 0 0 0".parse().unwrap();
+    assert_eq!(&[v("A1"), v("B1")], info.explored_moves[0].pv.as_slice());
+    assert_eq!(6, info.explored_moves[0].ownership.as_ref().unwrap().values().len());
+    assert!(info.explored_moves[1].ownership.is_none());
+    let info = info.with_board_size(RectSize::new(2, 3));
+    assert_eq!(Some(1.0), info.ownership.as_ref().unwrap().get(v("A3")));
+    assert_eq!(Some(0.5), info.explored_moves[0].ownership.as_ref().unwrap().get(v("A1")));
+    assert!(info.to_string().contains("pv A1 B1 movesOwnership 0.1 0.2"));
+}
+
+#[test]
+fn test_info_analysis() {
+    use crate::model::{Color, Info, Perspective, Vertex};
+
+    let v = |s: &str| s.parse::<Vertex>().unwrap();
+    let info: Info = "info move Q16 visits 30 winrate 0.4 scoreLead -1.5 order 1 pv Q16 \
+                      info move D4 visits 60 winrate 0.45 scoreLead -0.5 order 0 pv D4 \
+                      info move C3 visits 10 winrate 0.2 scoreLead -6 order 2 pv C3 \
+                      ownership 0.5 -0.5 0.5 -0.5".parse().unwrap();
+    assert_eq!(v("D4"), info.best_move().unwrap().coord);
+    assert_eq!(vec![v("D4"), v("Q16"), v("C3")], info.ranked_moves().iter().map(|m| m.coord).collect::<Vec<_>>());
+    assert_eq!(30, info.get(v("Q16")).unwrap().visits);
+    assert!(info.get(v("A1")).is_none());
+    assert_eq!(vec![v("D4"), v("Q16")], info.moves_with_visits(20).iter().map(|m| m.coord).collect::<Vec<_>>());
+    assert_eq!(100, info.total_visits());
+    assert_eq!(vec![(v("D4"), 0.6), (v("Q16"), 0.3), (v("C3"), 0.1)], info.visit_shares());
+    assert_eq!(Some(5.5), info.point_loss(v("C3")));
+    assert_eq!(Some(0.0), info.point_loss(v("D4")));
+    assert!((info.winrate_loss(v("Q16")).unwrap() - 0.05).abs() < 1e-6);
+    assert_eq!(None, info.point_loss(v("A1")));
+
+    // White to move, reported for Black
+    let white = info.clone().with_perspective(Perspective::Black, Perspective::SideToMove, Color::White);
+    assert!((white.best_move().unwrap().winrate - 0.55).abs() < 1e-6);
+    assert_eq!(0.5, white.best_move().unwrap().score_lead);
+    assert_eq!(Some(0.5), white.ownership.as_ref().unwrap().get(v("A2")));
+    let unchanged = info.clone().with_perspective(Perspective::SideToMove, Perspective::SideToMove, Color::White);
+    assert_eq!(-0.5, unchanged.best_move().unwrap().score_lead);
+    assert_eq!(Some(0.5), unchanged.ownership.as_ref().unwrap().get(v("A2")));
+    let reported_for_white = unchanged.ownership_from(Perspective::SideToMove, Color::White);
+    assert_eq!(Some(-0.5), reported_for_white.ownership.as_ref().unwrap().get(v("A2")));
+    assert_eq!(Some(Color::White), reported_for_white.ownership.as_ref().unwrap().owner(v("A2"), 0.5));
+    let reported_for_black = info.clone().ownership_from(Perspective::SideToMove, Color::Black);
+    assert_eq!(Some(0.5), reported_for_black.ownership.as_ref().unwrap().get(v("A2")));
+    let same = info.clone().with_perspective(Perspective::Black, Perspective::SideToMove, Color::Black);
+    assert_eq!(-0.5, same.best_move().unwrap().score_lead);
+    let back = white.with_perspective(Perspective::White, Perspective::Black, Color::Black);
+    assert_eq!(-0.5, back.best_move().unwrap().score_lead);
+    assert_eq!(Some(0.5), back.ownership.as_ref().unwrap().get(v("A2")));
+}
+
+#[test]
+fn test_time_control() {
+    use alloc::string::ToString;
+    use core::time::Duration;
+    use crate::model::{Color, GameClock, GtpCommand, TimeControl};
+
+    let s = Duration::from_secs;
+    let command: GtpCommand = "kgs-time_settings byoyomi 600 30 5".parse().unwrap();
+    let japanese = TimeControl::Japanese { main: s(600), period: s(30), periods: 5 };
+    assert_eq!(GtpCommand::KgsTimeSettings(japanese), command);
+    assert_eq!("kgs-time_settings byoyomi 600 30 5\n", command.to_string());
+    assert_eq!(GtpCommand::TimeSettings { main: 600, byo_yomi: 30, stones: 1 }, japanese.to_command(false));
+    assert!("kgs-time_settings byoyomi 600 30".parse::<GtpCommand>().is_err());
+    let fischer: TimeControl = "fischer 60 5".parse().unwrap();
+    assert_eq!(GtpCommand::TimeSettings { main: 60, byo_yomi: 0, stones: 0 }, fischer.to_command(true));
+    assert_eq!(GtpCommand::TimeSettings { main: 0, byo_yomi: 1, stones: 0 }, TimeControl::None.to_time_settings());
+
+    let mut clock = GameClock::new(fischer);
+    assert!(clock.record_move(Color::Black, s(20)));
+    assert_eq!(Some(GtpCommand::TimeLeft { color: Color::Black, time: 45, stones: 0 }), clock.time_left(Color::Black));
+    assert!(!clock.record_move(Color::Black, s(46)));
+    assert!(clock.get(Color::Black).flagged);
+    assert_eq!(s(60), clock.get(Color::White).remaining);
+
+    // 10s of main time then 2 moves per 20s
+    let mut clock = GameClock::new("canadian 10 20 2".parse().unwrap());
+    assert!(clock.record_move(Color::White, s(15)));
+    assert_eq!(Some(GtpCommand::TimeLeft { color: Color::White, time: 15, stones: 1 }), clock.time_left(Color::White));
+    assert!(clock.record_move(Color::White, s(14)));
+    assert_eq!((s(20), 2), (clock.get(Color::White).remaining, clock.get(Color::White).stones));
+    assert!(!clock.record_move(Color::White, s(21)));
+
+    let mut clock = GameClock::new(japanese);
+    assert!(clock.record_move(Color::Black, s(610)));
+    assert_eq!((s(30), 5, true), (clock.get(Color::Black).remaining, clock.get(Color::Black).stones, clock.get(Color::Black).in_byo_yomi));
+    assert!(clock.record_move(Color::Black, s(70)));
+    assert_eq!(3, clock.get(Color::Black).stones);
+    assert!(!clock.record_move(Color::Black, s(91)));
+    assert_eq!(None, GameClock::new(TimeControl::None).time_left(Color::Black));
+}
+
+#[cfg(feature = "wasm")]
+#[test]
+fn test_wasm_shapes() {
+    use crate::model::{Answer, Command, Info};
+    use alloc::string::{String, ToString};
+    use serde_json::json;
+
+    // the values parseCommand, parseAnswer and parseInfo give to JS
+    let command: Command = "3 play W Q16".parse().unwrap();
+    let value = serde_json::to_value(&command).unwrap();
+    assert_eq!(json!({"id": 3, "name": "Play", "args": {"Entity": "W Q16"}}), value);
+    assert_eq!("3 play W Q16\n", serde_json::from_value::<Command>(value).unwrap().to_string());
+
+    let answer = crate::wasm::answer("=3 D4\n\n", Some(String::from("genmove"))).unwrap();
+    assert_eq!(json!({"Response": {"id": 3, "data": {"Vertex": "D4"}}}), serde_json::to_value(&answer).unwrap());
+    let answer = crate::wasm::answer("? nope\n\n", None).unwrap();
+    let value = serde_json::to_value(&answer).unwrap();
+    assert_eq!(json!({"Failure": {"id": null, "message": "nope"}}), value);
+    assert_eq!("? nope\n\n", serde_json::from_value::<Answer>(value).unwrap().to_string());
+    assert!(crate::wasm::answer("garbage", None).is_err());
+
+    let info: Info = "info move D4 visits 10 winrate 0.5 pv D4 Q16 ownership 0.5 -0.5".parse().unwrap();
+    let value = serde_json::to_value(&info).unwrap();
+    assert_eq!(json!("D4"), value["explored_moves"][0]["coord"]);
+    assert_eq!(json!(["D4", "Q16"]), value["explored_moves"][0]["pv"]);
+    assert_eq!(json!({"size": [2, 1], "values": [0.5, -0.5]}), value["ownership"]);
+    assert!(serde_json::from_value::<Info>(value).unwrap().to_string().starts_with("info move D4 visits 10"));
 }
@@ -0,0 +1,227 @@
+//! A scripted GTP engine, to test controllers without a real engine.
+//!
+//! A script lists the expected commands, each followed by the engine's answer:
+//!
+//! ```text
+//! # lines before the first command run at startup
+//! @stderr GTP ready, beginning main protocol loop
+//! > list_commands
+//! = name
+//! play
+//! > genmove b
+//! @delay 50
+//! = D4
+//! > play W D4
+//! ? illegal move
+//! > kata-analyze 10
+//! =
+//! info move Q16 visits 12 winrate 0.5
+//! > *
+//! @crash
+//! ```
+//!
+//! `> *` accepts any command and a command without answer lines gets an
+//! empty success. Answers get the id of the command they answer
+//! and their terminating empty line; analysis answers are only terminated
+//! when the next command arrives, as KataGo does. `@delay <ms>` waits before
+//! going on, `@stderr <text>` writes a line on stderr and `@crash` stops the
+//! engine without answering.
+
+use crate::model::preprocess;
+use crate::model::CommandName;
+use crate::model::ParseError;
+use crate::model::ParseErrorKind;
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockAction {
+    /// A line written on stdout.
+    Reply(String),
+    Stderr(String),
+    Delay(Duration),
+    Crash,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockExchange {
+    /// The expected command without its id, `None` accepting any command.
+    pub expected: Option<String>,
+    pub actions: Vec<MockAction>,
+}
+
+/// How a mock session ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockExit {
+    /// The controller closed the engine's input.
+    Eof,
+    Quit,
+    Crash,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MockEngine {
+    prelude: Vec<MockAction>,
+    exchanges: Vec<MockExchange>,
+    next: usize,
+    mismatches: Vec<String>,
+}
+
+/// The command with its id removed and whitespace normalized.
+fn normalize(command: &str) -> (Option<&str>, String) {
+    let mut tokens = command.split_ascii_whitespace().peekable();
+    let id = tokens.next_if(|t| t.parse::<u32>().is_ok());
+    (id, tokens.collect::<Vec<_>>().join(" "))
+}
+
+impl FromStr for MockEngine {
+    type Err = ParseError;
+
+    fn from_str(script: &str) -> Result<Self, Self::Err> {
+        let mut engine = Self::default();
+        for line in script.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let action = if let Some(command) = trimmed.strip_prefix('>') {
+                let command = normalize(command).1;
+                engine.exchanges.push(MockExchange {
+                    expected: if command == "*" { None } else { Some(command) },
+                    actions: Vec::new(),
+                });
+                continue;
+            } else if let Some(ms) = trimmed.strip_prefix("@delay") {
+                let ms = ms.trim().parse::<u64>()
+                    .map_err(|e| ParseError::from(e).expected("@delay <milliseconds>").at_token(script, ms))?;
+                MockAction::Delay(Duration::from_millis(ms))
+            } else if let Some(text) = trimmed.strip_prefix("@stderr") {
+                MockAction::Stderr(text.trim_start().to_string())
+            } else if trimmed == "@crash" {
+                MockAction::Crash
+            } else if trimmed.starts_with('@') {
+                return Err(ParseError::new(ParseErrorKind::WrongArgs)
+                    .expected("@delay, @stderr or @crash")
+                    .at_token(script, trimmed));
+            } else {
+                MockAction::Reply(line.trim_end().to_string())
+            };
+
+            match engine.exchanges.last_mut() {
+                Some(exchange) => exchange.actions.push(action),
+                None => engine.prelude.push(action),
+            }
+        }
+        Ok(engine)
+    }
+}
+
+impl MockEngine {
+    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self, std::io::Error> {
+        std::fs::read_to_string(path)?
+            .parse()
+            .map_err(|e: ParseError| std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string()))
+    }
+
+    pub fn exchanges(&self) -> &[MockExchange] {
+        &self.exchanges
+    }
+
+    /// Commands received while another one was expected.
+    pub fn mismatches(&self) -> &[String] {
+        &self.mismatches
+    }
+
+    /// Whether every scripted command was received.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.exchanges.len()
+    }
+
+    /// Plays the script, reading commands from `input` and answering on `output`.
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write, mut errors: impl Write) -> Result<MockExit, std::io::Error> {
+        let prelude = std::mem::take(&mut self.prelude);
+        let crashed = Self::perform(&prelude, None, &mut output, &mut errors)?;
+        self.prelude = prelude;
+        if crashed {
+            return Ok(MockExit::Crash);
+        }
+
+        let mut analysing = false;
+        for line in input.lines() {
+            let line = preprocess(&line?);
+            if line.trim().is_empty() {
+                continue;
+            }
+            if analysing {
+                writeln!(output)?;
+                analysing = false;
+            }
+
+            let (id, command) = normalize(&line);
+            let exchange = match self.exchanges.get(self.next) {
+                Some(exchange) if exchange.expected.as_ref().map(|e| *e == command).unwrap_or(true) => exchange,
+                Some(exchange) => {
+                    let expected = exchange.expected.clone().unwrap_or_default();
+                    self.mismatches.push(command.clone());
+                    write!(output, "?{} unexpected command {}, expected {}\n\n", id.unwrap_or(""), command, expected)?;
+                    output.flush()?;
+                    continue;
+                },
+                None => {
+                    self.mismatches.push(command.clone());
+                    write!(output, "?{} unexpected command {}, end of script\n\n", id.unwrap_or(""), command)?;
+                    output.flush()?;
+                    continue;
+                },
+            };
+            self.next += 1;
+
+            if Self::perform(&exchange.actions, Some(id.unwrap_or("")), &mut output, &mut errors)? {
+                return Ok(MockExit::Crash);
+            }
+            let name = command.split_ascii_whitespace().next().map(|n| CommandName::from(n.to_string())).unwrap_or(CommandName::Unknown);
+            let answered = exchange.actions.iter().any(|a| matches!(a, MockAction::Reply(_)));
+            match name {
+                CommandName::LzAnalyze |
+                CommandName::KataAnalyze |
+                CommandName::Analyze if answered => analysing = true,
+                _ if answered => writeln!(output)?,
+                _ => write!(output, "={}\n\n", id.unwrap_or(""))?,
+            }
+            output.flush()?;
+
+            if name == CommandName::Quit {
+                return Ok(MockExit::Quit);
+            }
+        }
+
+        if analysing {
+            writeln!(output)?;
+        }
+        Ok(MockExit::Eof)
+    }
+
+    /// Runs `actions`, putting `id` in the first answer line. Returns whether it crashed.
+    fn perform(actions: &[MockAction], mut id: Option<&str>, output: &mut impl Write, errors: &mut impl Write) -> Result<bool, std::io::Error> {
+        for action in actions {
+            match action {
+                MockAction::Reply(line) => {
+                    match id.take() {
+                        Some(id) if line.starts_with(['=', '?']) => writeln!(output, "{}{}{}", &line[..1], id, &line[1..])?,
+                        _ => writeln!(output, "{}", line)?,
+                    }
+                    output.flush()?;
+                },
+                MockAction::Stderr(text) => {
+                    writeln!(errors, "{}", text)?;
+                    errors.flush()?;
+                },
+                MockAction::Delay(duration) => std::thread::sleep(*duration),
+                MockAction::Crash => return Ok(true),
+            }
+        }
+        Ok(false)
+    }
+}
@@ -0,0 +1,375 @@
+use crate::model::Color;
+use crate::model::Failure;
+use crate::model::Move;
+use crate::model::RectSize;
+use crate::model::Vertex;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum KoRule {
+    /// Only the immediate recapture of a single stone is forbidden.
+    #[default]
+    Simple,
+    /// A move may not recreate any earlier board position.
+    PositionalSuperko,
+    /// A move may not recreate an earlier position with the same player to move.
+    SituationalSuperko,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IllegalMove {
+    OffBoard,
+    Occupied,
+    Suicide,
+    Ko,
+    Superko,
+}
+
+impl Display for IllegalMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::OffBoard => write!(f, "illegal move: off board"),
+            Self::Occupied => write!(f, "illegal move: occupied"),
+            Self::Suicide =>  write!(f, "illegal move: suicide"),
+            Self::Ko =>       write!(f, "illegal move: ko"),
+            Self::Superko =>  write!(f, "illegal move: superko"),
+        }
+    }
+}
+
+impl core::error::Error for IllegalMove {}
+
+/// A board size engines cannot play on, see [`RectSize::is_valid`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvalidBoardSize(pub RectSize);
+
+impl Display for InvalidBoardSize {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid board size {}", self.0)
+    }
+}
+
+impl core::error::Error for InvalidBoardSize {}
+
+impl IllegalMove {
+    /// The failure an engine answers `play` with for this move.
+    pub fn to_failure(&self) -> Failure {
+        Failure::new(self.to_string())
+    }
+}
+
+// splitmix64, used to derive zobrist keys without storing a table
+const fn zobrist(index: usize, color: Color) -> u64 {
+    let mut z = (index as u64 * 2 + color as u64 + 1).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A go board of up to 25x25 points, applying moves with captures and
+/// checking their legality.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedBoard"))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+    size: RectSize,
+    points: Vec<Option<Color>>,
+    ko: Option<(Vertex, Color)>,
+    ko_rule: KoRule,
+    hash: u64,
+    history: Vec<(u64, Color)>,
+}
+
+/// Deserialized fields, checked against the size. The hash is recomputed.
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedBoard {
+    size: RectSize,
+    points: Vec<Option<Color>>,
+    ko: Option<(Vertex, Color)>,
+    ko_rule: KoRule,
+    history: Vec<(u64, Color)>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedBoard> for Board {
+    type Error = &'static str;
+
+    fn try_from(unchecked: UncheckedBoard) -> Result<Self, Self::Error> {
+        let mut board = Self::try_new(unchecked.size).map_err(|_| "invalid board size")?;
+        if unchecked.points.len() != board.points.len() {
+            return Err("not one point per intersection");
+        }
+        if unchecked.ko.is_some_and(|(vertex, _)| vertex.to_index(board.size).is_none()) {
+            return Err("ko point off the board");
+        }
+        for (index, stone) in unchecked.points.into_iter().enumerate() {
+            board.put(index, stone);
+        }
+        board.ko = unchecked.ko;
+        board.ko_rule = unchecked.ko_rule;
+        board.history = unchecked.history;
+        Ok(board)
+    }
+}
+
+impl Board {
+    /// # Panics
+    ///
+    /// If `size` is not [valid](RectSize::is_valid), see [`Board::try_new`].
+    pub fn new(size: RectSize) -> Self {
+        Self::try_new(size).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(size: RectSize) -> Result<Self, InvalidBoardSize> {
+        if !size.is_valid() {
+            return Err(InvalidBoardSize(size));
+        }
+        Ok(Self {
+            size,
+            points: vec![None; size.area()],
+            ko: None,
+            ko_rule: KoRule::default(),
+            hash: 0,
+            history: vec![(0, Color::Black)],
+        })
+    }
+
+    pub fn square(size: u32) -> Self {
+        Self::new(RectSize::square(size))
+    }
+
+    pub fn with_ko_rule(mut self, ko_rule: KoRule) -> Self {
+        self.ko_rule = ko_rule;
+        self
+    }
+
+    pub const fn size(&self) -> RectSize {
+        self.size
+    }
+
+    pub const fn ko_rule(&self) -> KoRule {
+        self.ko_rule
+    }
+
+    /// The point the given player may not play on because of simple ko.
+    pub fn ko(&self, color: Color) -> Option<Vertex> {
+        match self.ko {
+            Some((vertex, forbidden)) if forbidden == color => Some(vertex),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, vertex: Vertex) -> Option<Color> {
+        vertex.to_index(self.size).and_then(|i| self.points[i])
+    }
+
+    /// Places or removes a stone without any legality check, as for setup
+    /// stones and handicap. The position history used for superko restarts.
+    pub fn set(&mut self, vertex: Vertex, stone: Option<Color>, to_move: Color) -> Result<(), IllegalMove> {
+        let index = vertex.to_index(self.size).ok_or(IllegalMove::OffBoard)?;
+        self.put(index, stone);
+        self.ko = None;
+        self.history = vec![(self.hash, to_move)];
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.iter().all(|p| p.is_none())
+    }
+
+    pub fn stones(&self, color: Color) -> impl Iterator<Item = Vertex> + '_ {
+        let size = self.size;
+        self.points.iter()
+            .enumerate()
+            .filter(move |(_, p)| **p == Some(color))
+            .filter_map(move |(i, _)| Vertex::from_index(i, size))
+    }
+
+    pub fn count(&self, color: Color) -> usize {
+        self.points.iter().filter(|p| **p == Some(color)).count()
+    }
+
+    /// Checks a move without playing it.
+    pub fn check(&self, mov: Move) -> Result<(), IllegalMove> {
+        self.clone().play(mov).map(|_| ())
+    }
+
+    pub fn is_legal(&self, mov: Move) -> bool {
+        self.check(mov).is_ok()
+    }
+
+    /// Plays a move, returning the captured stones.
+    /// Passing and resigning leave the board unchanged.
+    pub fn play(&mut self, mov: Move) -> Result<Vec<Vertex>, IllegalMove> {
+        let Move { color, vertex } = mov;
+        if !vertex.is_coord() {
+            self.ko = None;
+            return Ok(Vec::new());
+        }
+
+        let index = vertex.to_index(self.size).ok_or(IllegalMove::OffBoard)?;
+        if self.points[index].is_some() {
+            return Err(IllegalMove::Occupied);
+        }
+        if self.ko(color) == Some(vertex) {
+            return Err(IllegalMove::Ko);
+        }
+
+        let mut next = self.clone();
+        next.put(index, Some(color));
+
+        let mut captured = Vec::new();
+        for n in next.neighbours(index) {
+            if next.points[n] == Some(color.opponent()) && next.liberties(n) == 0 {
+                for stone in next.group(n) {
+                    next.put(stone, None);
+                    captured.push(stone);
+                }
+            }
+        }
+
+        if next.liberties(index) == 0 {
+            return Err(IllegalMove::Suicide);
+        }
+
+        let to_move = color.opponent();
+        let repeated = match self.ko_rule {
+            KoRule::Simple => false,
+            KoRule::PositionalSuperko => self.history.iter().any(|(h, _)| *h == next.hash),
+            KoRule::SituationalSuperko => self.history.contains(&(next.hash, to_move)),
+        };
+        if repeated {
+            return Err(IllegalMove::Superko);
+        }
+
+        next.ko = None;
+        if captured.len() == 1 && next.group(index).len() == 1 && next.liberties(index) == 1 {
+            let ko_point = Vertex::from_index(captured[0], self.size);
+            next.ko = ko_point.map(|v| (v, to_move));
+        }
+        next.history.push((next.hash, to_move));
+        *self = next;
+
+        Ok(captured.into_iter().filter_map(|i| Vertex::from_index(i, self.size)).collect())
+    }
+
+    /// All the stones connected to the one on `vertex`.
+    pub fn group_at(&self, vertex: Vertex) -> Vec<Vertex> {
+        match vertex.to_index(self.size) {
+            Some(i) if self.points[i].is_some() =>
+                self.group(i).into_iter().filter_map(|i| Vertex::from_index(i, self.size)).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn liberties_at(&self, vertex: Vertex) -> usize {
+        match vertex.to_index(self.size) {
+            Some(i) if self.points[i].is_some() => self.liberties(i),
+            _ => 0,
+        }
+    }
+
+    pub fn neighbours_of(&self, vertex: Vertex) -> Vec<Vertex> {
+        match vertex.to_index(self.size) {
+            Some(i) => self.neighbours(i).into_iter().filter_map(|i| Vertex::from_index(i, self.size)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn put(&mut self, index: usize, stone: Option<Color>) {
+        if let Some(old) = self.points[index] {
+            self.hash ^= zobrist(index, old);
+        }
+        if let Some(new) = stone {
+            self.hash ^= zobrist(index, new);
+        }
+        self.points[index] = stone;
+    }
+
+    fn neighbours(&self, index: usize) -> Vec<usize> {
+        let width = self.size.width() as usize;
+        let (row, col) = (index / width, index % width);
+        let mut neighbours = Vec::with_capacity(4);
+        if row > 0 {
+            neighbours.push(index - width);
+        }
+        if row + 1 < self.size.height() as usize {
+            neighbours.push(index + width);
+        }
+        if col > 0 {
+            neighbours.push(index - 1);
+        }
+        if col + 1 < width {
+            neighbours.push(index + 1);
+        }
+        neighbours
+    }
+
+    fn group(&self, index: usize) -> Vec<usize> {
+        let color = self.points[index];
+        let mut seen = vec![false; self.points.len()];
+        let mut stack = vec![index];
+        let mut group = Vec::new();
+        seen[index] = true;
+        while let Some(i) = stack.pop() {
+            group.push(i);
+            for n in self.neighbours(i) {
+                if !seen[n] && self.points[n] == color {
+                    seen[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+        group
+    }
+
+    fn liberties(&self, index: usize) -> usize {
+        let mut seen = vec![false; self.points.len()];
+        let mut liberties = 0;
+        for stone in self.group(index) {
+            for n in self.neighbours(stone) {
+                if self.points[n].is_none() && !seen[n] {
+                    seen[n] = true;
+                    liberties += 1;
+                }
+            }
+        }
+        liberties
+    }
+}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let height = self.size.height() as usize;
+        let width = self.size.width() as usize;
+        for row in 0..height {
+            write!(f, "{:>2}", height - row)?;
+            for col in 0..width {
+                let c = match self.points[row * width + col] {
+                    Some(Color::Black) => 'X',
+                    Some(Color::White) => 'O',
+                    None => '.',
+                };
+                write!(f, " {}", c)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "  ")?;
+        for col in 0..width {
+            if let Some(v) = Vertex::from_row_col(height.saturating_sub(1), col, self.size) {
+                let label = v.to_string();
+                write!(f, " {}", &label[..1])?;
+            }
+        }
+        writeln!(f)
+    }
+}
@@ -1,3 +1,5 @@
+use crate::model::ParseError;
+use crate::model::ParseErrorKind;
 use crate::model::types::List;
 use crate::model::types::Collection;
 use crate::model::types::Move;
@@ -207,7 +209,7 @@ impl Args {
 }
 
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct RectSize((u32, u32));
 impl FromStr for RectSize {
     type Err = crate::model::ParseError;
@@ -216,25 +218,64 @@ impl FromStr for RectSize {
         let width = if let Some(s) = matches.next() {
             s.parse()?
         } else {
-            return Err(Self::Err::WrongAlternative)
+            return Err(ParseErrorKind::WrongAlternative.into())
         };
         let height = if let Some(s) = matches.next() {
             s.parse()?
         } else {
-            return Err(Self::Err::WrongAlternative)
+            return Err(ParseErrorKind::WrongAlternative.into())
         };
         Ok(Self((width, height)))
     }
 }
 
 impl Display for RectSize {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         write!(f, "{} {}", self.0.0, self.0.1)
     }
 }
 
+impl RectSize {
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self((width, height))
+    }
+
+    pub const fn square(size: u32) -> Self {
+        Self((size, size))
+    }
+
+    /// Whether engines can play on a board of this size.
+    pub const fn is_valid(&self) -> bool {
+        self.0.0 >= 1 && self.0.1 >= 1
+            && self.0.0 <= crate::model::MAX_BOARD_SIZE
+            && self.0.1 <= crate::model::MAX_BOARD_SIZE
+    }
+
+    pub const fn area(&self) -> usize {
+        self.0.0 as usize * self.0.1 as usize
+    }
+
+    pub fn contains(&self, vertex: &Vertex) -> bool {
+        vertex.is_on_board(*self)
+    }
+
+    /// All on-board vertices in row-major order from the top left corner.
+    pub fn vertices(&self) -> impl Iterator<Item = Vertex> {
+        let size = *self;
+        (0..self.area()).filter_map(move |i| Vertex::from_index(i, size))
+    }
+
+    pub const fn width(&self) -> u32 {
+        self.0.0
+    }
+
+    pub const fn height(&self) -> u32 {
+        self.0.1
+    }
+}
+
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum CommandName {
     ProtocolVersion,
     Name,
@@ -332,7 +373,7 @@ impl FromStr for CommandName {
             "get_komi" => Ok(Self::GetKomi),
             "kata-list_time_settings" => Ok(Self::KataListTimeSettings),
             "kata-set-param" => Ok(Self::KataSetParam),
-            _ => Err(Self::Err::WrongAlternative),
+            _ => Err(ParseErrorKind::WrongAlternative.into()),
         }
     }
 }
@@ -419,7 +460,7 @@ impl Display for CommandName {
             Self::SetPosition =>          write!(f, "set_position"),
             Self::ClearCache =>           write!(f, "clear_cache"),
             Self::Stop =>                 write!(f, "stop"),
-            Self::KataGetRules =>         write!(f, "kata-get_rules"),
+            Self::KataGetRules =>         write!(f, "kata-get-rules"),
             Self::KataSetRules =>         write!(f, "kata-set-rules"),
             Self::KataSetRule =>          write!(f, "kata-set-rule"),
             Self::KgsRules =>             write!(f, "kgs-rules"),
@@ -451,15 +492,14 @@ pub struct Command {
 
 impl Display for Command {
     fn fmt(&self,  f: &mut core::fmt::Formatter) -> core::fmt::Result {
-        if self.id.is_some() {
-            write!(f, "{} ", self.id.unwrap())?;
+        if let Some(id) = self.id {
+            write!(f, "{} ", id)?;
         }
         write!(f, "{}", self.name)?;
-        if self.args.is_some() {
-            write!(f, " {}", self.args.clone().unwrap())?;
+        if let Some(args) = &self.args {
+            write!(f, " {}", args)?;
         }
-        write!(f, "\n")?;
-        Ok(())
+        writeln!(f)
     }
 }
 
@@ -467,16 +507,26 @@ impl FromStr for Command {
     type Err = crate::model::ParseError;
 
     fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let str = crate::model::preprocess(str);
         let mut matches : Vec<_> = str.split_ascii_whitespace().collect();
+        if matches.is_empty() {
+            return Err(ParseErrorKind::EmptyString.into());
+        }
         let mut id = None;
         if let Ok(has_id) = matches[0].parse::<u32>() {
             id = Some(has_id);
             matches.remove(0);
         }
+        if matches.is_empty() {
+            return Err(ParseErrorKind::WrongCommandName.into());
+        }
         let name = matches.remove(0);
         let args = matches.join(" ");
+        let args_offset = matches.first()
+            .map(|arg| arg.as_ptr() as usize - str.as_ptr() as usize)
+            .unwrap_or(str.len());
 
-        match name {
+        let parse = || match name {
             "protocol_version" |
             "name" |
             "version" |
@@ -492,8 +542,7 @@ impl FromStr for Command {
             "cputime" |
             "gomill-cpu_time" |
             "get_komi" |
-            "kata-list_time_settings" |
-            "kata-list-params"
+            "kata-list_time_settings"
                 => Ok(Self {
                         id,
                         name: CommandName::from_str(name).unwrap(),
@@ -504,7 +553,7 @@ impl FromStr for Command {
                 => Ok(Self {
                         id,
                         name: CommandName::from_str(name).unwrap(),
-                        args: Some(Args::string(args)),
+                        args: Some(Args::string(args.clone())),
                     }),
             "boardsize" |
             "fixed_handicap" |
@@ -545,7 +594,7 @@ impl FromStr for Command {
             "set_position" => Ok(Self {
                         id,
                         name: CommandName::from_str(name).unwrap(),
-                        args: Some(Args::list_move(args.as_str().parse()?)),
+                        args: Some(Args::list_move(parse_moves(&matches)?)),
             }),
             "rectangular_boardsize" => Ok(Self {
                         id,
@@ -570,9 +619,22 @@ impl FromStr for Command {
                         name: CommandName::from_str(name).unwrap(),
                         args: Some(Args::list_string(args.as_str().parse()?)),
                 }),
-            _ => Err(crate::model::ParseError::WrongCommandName) //unreachable
-        }
+            _ => Err(ParseError::new(ParseErrorKind::WrongCommandName).at_token(&str, name))
+        };
+        parse().map_err(|e| match e.kind() {
+            ParseErrorKind::WrongCommandName => e,
+            _ => e.with_input(&args).at(args_offset),
+        })
+    }
+}
+
+pub(crate) fn parse_moves(tokens: &[&str]) -> Result<List<Move>, crate::model::ParseError> {
+    if !tokens.len().is_multiple_of(2) {
+        return Err(ParseErrorKind::WrongArgs.into());
     }
+    tokens.chunks(2)
+        .map(|m| Ok(Move { color: m[0].parse()?, vertex: m[1].parse()? }))
+        .collect()
 }
 
 impl From<&Command> for String {
@@ -581,9 +643,9 @@ impl From<&Command> for String {
     }
 }
 
-impl Into<String> for Command {
-    fn into(self) -> String {
-        self.to_string()
+impl From<Command> for String {
+    fn from(command: Command) -> Self {
+        command.to_string()
     }
 }
 
@@ -0,0 +1,200 @@
+use alloc::string::String;
+use alloc::string::ToString;
+use core::fmt;
+use core::fmt::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// What went wrong, without any context. Cheap to copy and match on.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParseErrorKind {
+    WrongAlternative,
+    WrongColor,
+    WrongCommandName,
+    WrongCoordinates,
+    WrongSimpleEntity,
+    WrongArgs,
+    WrongBool,
+    WrongResponseData,
+    WrongResponseFormat,
+    WrongFailureFormat,
+    WrongAnswerFormat,
+    WrongScore,
+    WrongSgf,
+    EmptyString,
+}
+
+impl Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::WrongAlternative =>    write!(f, "unknown alternative"),
+            Self::WrongColor =>          write!(f, "invalid color"),
+            Self::WrongCommandName =>    write!(f, "unknown command name"),
+            Self::WrongCoordinates =>    write!(f, "invalid coordinates"),
+            Self::WrongSimpleEntity =>   write!(f, "invalid vertex, color or move"),
+            Self::WrongArgs =>           write!(f, "invalid arguments"),
+            Self::WrongBool =>           write!(f, "invalid boolean"),
+            Self::WrongResponseData =>   write!(f, "invalid response data"),
+            Self::WrongResponseFormat => write!(f, "invalid response format"),
+            Self::WrongFailureFormat =>  write!(f, "invalid failure format"),
+            Self::WrongAnswerFormat =>   write!(f, "invalid answer format"),
+            Self::WrongScore =>          write!(f, "invalid score"),
+            Self::WrongSgf =>            write!(f, "invalid SGF"),
+            Self::EmptyString =>         write!(f, "empty input"),
+        }
+    }
+}
+
+/// Lower level error which caused a [`ParseError`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorCause {
+    Int(core::num::ParseIntError),
+    Float(core::num::ParseFloatError),
+}
+
+impl Display for ParseErrorCause {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Int(e) => write!(f, "{}", e),
+            Self::Float(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    input: Option<String>,
+    offset: Option<usize>,
+    expected: Option<&'static str>,
+    cause: Option<ParseErrorCause>,
+}
+
+impl ParseError {
+    pub const fn new(kind: ParseErrorKind) -> Self {
+        Self {
+            kind,
+            input: None,
+            offset: None,
+            expected: None,
+            cause: None,
+        }
+    }
+
+    /// Sets the offending input, unless a more precise one is already known.
+    pub fn with_input(mut self, input: &str) -> Self {
+        if self.input.is_none() {
+            self.input = Some(input.to_string());
+        }
+        self
+    }
+
+    /// Sets what the parser expected, unless already known.
+    pub fn expected(mut self, expected: &'static str) -> Self {
+        if self.expected.is_none() {
+            self.expected = Some(expected);
+        }
+        self
+    }
+
+    /// Records that the error happened at `offset` bytes into the input.
+    /// Calling it again while going up the parser stack adds the offset of the
+    /// inner input within the outer one.
+    pub fn at(mut self, offset: usize) -> Self {
+        self.offset = Some(self.offset.unwrap_or(0).saturating_add(offset));
+        self
+    }
+
+    /// Records `token` as the offending input and its position within `base`.
+    /// `token` must be a subslice of `base`.
+    pub fn at_token(self, base: &str, token: &str) -> Self {
+        let offset = (token.as_ptr() as usize).saturating_sub(base.as_ptr() as usize);
+        self.with_input(token).at(offset)
+    }
+
+    pub const fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    pub fn input(&self) -> Option<&str> {
+        self.input.as_deref()
+    }
+
+    pub const fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    pub const fn expected_what(&self) -> Option<&'static str> {
+        self.expected
+    }
+
+    pub const fn cause(&self) -> Option<&ParseErrorCause> {
+        self.cause.as_ref()
+    }
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if let Some(expected) = self.expected {
+            write!(f, ", expected {}", expected)?;
+        }
+        if let Some(input) = &self.input {
+            write!(f, ", found {:?}", input)?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {}", offset)?;
+        }
+        if let Some(cause) = &self.cause {
+            write!(f, " ({})", cause)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match &self.cause {
+            Some(ParseErrorCause::Int(e)) => Some(e),
+            Some(ParseErrorCause::Float(e)) => Some(e),
+            None => None,
+        }
+    }
+}
+
+impl PartialEq<ParseErrorKind> for ParseError {
+    fn eq(&self, kind: &ParseErrorKind) -> bool {
+        self.kind == *kind
+    }
+}
+
+impl From<ParseErrorKind> for ParseError {
+    fn from(kind: ParseErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl From<core::num::ParseIntError> for ParseError {
+    fn from(err: core::num::ParseIntError) -> Self {
+        Self {
+            cause: Some(ParseErrorCause::Int(err)),
+            ..Self::new(ParseErrorKind::WrongArgs).expected("an integer")
+        }
+    }
+}
+
+impl From<core::num::ParseFloatError> for ParseError {
+    fn from(err: core::num::ParseFloatError) -> Self {
+        Self {
+            cause: Some(ParseErrorCause::Float(err)),
+            ..Self::new(ParseErrorKind::WrongArgs).expected("a number")
+        }
+    }
+}
+
+impl From<core::convert::Infallible> for ParseError {
+    fn from(err: core::convert::Infallible) -> Self {
+        match err {}
+    }
+}
@@ -1,9 +1,9 @@
+use crate::model::ParseErrorKind;
 use alloc::string::String;
 use alloc::string::ToString;
 use core::str::FromStr;
 use alloc::fmt;
 use alloc::fmt::Display;
-use alloc::vec::Vec;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -18,8 +18,8 @@ pub struct Failure {
 impl Display for Failure {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "?")?;
-        if self.id.is_some() {
-            write!(f, "{}", self.id.unwrap())?;
+        if let Some(id) = self.id {
+            write!(f, "{}", id)?;
         }
         write!(f, " {}\n\n", self.message)
     }
@@ -30,22 +30,21 @@ impl FromStr for Failure {
 
     fn from_str(str: &str) -> Result<Self, Self::Err> {
         if str.get(0..1) != Some("?") {
-            return Err(Self::Err::WrongFailureFormat);
+            return Err(ParseErrorKind::WrongFailureFormat.into());
         }
 
-        let str = str.split_at(1);
-        let str = str.1;
+        let str = &str[1..];
+        let id_len = str.find(|c: char| !c.is_ascii_digit()).unwrap_or(str.len());
+        let (id, message) = str.split_at(id_len);
+        let id = if id.is_empty() {
+            None
+        } else {
+            Some(id.parse()?)
+        };
 
-        let split: Vec<&str> = str.splitn(2, " ").collect();
-        let mut id = None;
-
-        if let Ok(has_id) = split[0].parse::<u32>() {
-            id = Some(has_id);
-        }
-        
         Ok(Self{
             id,
-            message: split[1].trim_end().to_string(),
+            message: message.trim().to_string(),
         })
     }
 }
@@ -0,0 +1,369 @@
+use crate::model::Board;
+use crate::model::Color;
+use crate::model::Command;
+use crate::model::GtpCommand;
+use crate::model::IllegalMove;
+use crate::model::InvalidBoardSize;
+use crate::model::KoRule;
+use crate::model::List;
+use crate::model::Move;
+use crate::model::RectSize;
+use crate::model::ResponseData;
+use crate::model::ScoreDetail;
+use crate::model::ScoringRules;
+use crate::model::Vertex;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HandicapError {
+    BoardNotEmpty,
+    InvalidNumberOfStones,
+    BadVertexList,
+}
+
+impl Display for HandicapError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::BoardNotEmpty =>         write!(f, "board not empty"),
+            Self::InvalidNumberOfStones => write!(f, "invalid number of stones"),
+            Self::BadVertexList =>         write!(f, "bad vertex list"),
+        }
+    }
+}
+
+impl core::error::Error for HandicapError {}
+
+/// Fixed handicap placement as defined by the GTP specification.
+/// Returns `None` when the board size does not allow that many stones.
+pub fn fixed_handicap_vertices(stones: u32, size: RectSize) -> Option<Vec<Vertex>> {
+    if size.width() != size.height() {
+        return None;
+    }
+    let n = size.width();
+    let max = if !(7..=crate::model::MAX_BOARD_SIZE).contains(&n) {
+        0
+    } else if n == 7 || n.is_multiple_of(2) {
+        4
+    } else {
+        9
+    };
+    if stones < 2 || stones > max {
+        return None;
+    }
+
+    let edge = if n >= 13 { 4 } else { 3 };
+    let (low, mid, high) = (edge as u8, n.div_ceil(2) as u8, (n + 1 - edge) as u8);
+    let corners = [(low, low), (high, high), (low, high), (high, low)];
+    let sides = [(low, mid), (high, mid), (mid, low), (mid, high)];
+
+    let mut vertices = corners[..stones.min(4) as usize].to_vec();
+    if stones >= 6 {
+        vertices.extend_from_slice(&sides[..if stones >= 8 { 4 } else { 2 }]);
+    }
+    if stones >= 5 && !stones.is_multiple_of(2) {
+        vertices.push((mid, mid));
+    }
+    Some(vertices.into_iter().map(|(x, y)| Vertex::Coord(x, y)).collect())
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+struct Snapshot {
+    board: Board,
+    captures: [u32; 2],
+    to_move: Color,
+    passes: u32,
+}
+
+/// A game record: the current board plus everything needed to undo moves
+/// and to replay the position on an engine.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Game {
+    board: Board,
+    komi: f32,
+    handicap: Vec<Vertex>,
+    setup: Vec<Move>,
+    moves: Vec<Move>,
+    captures: [u32; 2],
+    to_move: Color,
+    passes: u32,
+    history: Vec<Snapshot>,
+}
+
+impl Game {
+    /// # Panics
+    ///
+    /// If `size` is not [valid](RectSize::is_valid), see [`Game::try_new`].
+    pub fn new(size: RectSize) -> Self {
+        Self::try_new(size).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn try_new(size: RectSize) -> Result<Self, InvalidBoardSize> {
+        Ok(Self {
+            board: Board::try_new(size)?,
+            komi: 0.0,
+            handicap: Vec::new(),
+            setup: Vec::new(),
+            moves: Vec::new(),
+            captures: [0, 0],
+            to_move: Color::Black,
+            passes: 0,
+            history: Vec::new(),
+        })
+    }
+
+    pub fn with_komi(mut self, komi: f32) -> Self {
+        self.komi = komi;
+        self
+    }
+
+    pub fn with_ko_rule(mut self, ko_rule: KoRule) -> Self {
+        self.board = self.board.with_ko_rule(ko_rule);
+        self
+    }
+
+    pub const fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub const fn size(&self) -> RectSize {
+        self.board.size()
+    }
+
+    pub const fn komi(&self) -> f32 {
+        self.komi
+    }
+
+    pub fn set_komi(&mut self, komi: f32) {
+        self.komi = komi;
+    }
+
+    pub fn handicap(&self) -> &[Vertex] {
+        &self.handicap
+    }
+
+    /// Stones placed before the first move, other than handicap.
+    pub fn setup(&self) -> &[Move] {
+        &self.setup
+    }
+
+    pub fn moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn move_number(&self) -> usize {
+        self.moves.len()
+    }
+
+    pub const fn to_move(&self) -> Color {
+        self.to_move
+    }
+
+    pub fn set_to_move(&mut self, color: Color) {
+        self.to_move = color;
+    }
+
+    /// Number of stones captured by `color`.
+    pub const fn captures(&self, color: Color) -> u32 {
+        self.captures[color as usize]
+    }
+
+    pub const fn consecutive_passes(&self) -> u32 {
+        self.passes
+    }
+
+    /// Whether the game ended by two consecutive passes or a resignation.
+    pub fn is_over(&self) -> bool {
+        self.passes >= 2 || self.moves.last().map(|m| m.vertex == Vertex::Resign).unwrap_or(false)
+    }
+
+    /// Counts the current position under `rules`, with the game's komi
+    /// and captures. `dead` is typically the answer to `final_status_list dead`.
+    pub fn score(&self, rules: ScoringRules, dead: &[Vertex]) -> ScoreDetail {
+        let rules = ScoringRules { komi: self.komi, ..rules };
+        rules.score(&self.board, dead, self.captures, self.handicap.len() as u32)
+    }
+
+    /// Starts over on an empty board of `size`, keeping komi and ko rule.
+    /// Returns `false`, leaving the game as is, when the size is not valid.
+    fn resize(&mut self, size: RectSize) -> bool {
+        match Self::try_new(size) {
+            Ok(game) => {
+                *self = game.with_komi(self.komi).with_ko_rule(self.board.ko_rule());
+                true
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// Empties the board and forgets the moves, keeping size, komi and ko rule.
+    pub fn clear(&mut self) {
+        let ko_rule = self.board.ko_rule();
+        *self = Self::new(self.size()).with_komi(self.komi).with_ko_rule(ko_rule);
+    }
+
+    pub fn play(&mut self, mov: Move) -> Result<Vec<Vertex>, IllegalMove> {
+        let snapshot = Snapshot {
+            board: self.board.clone(),
+            captures: self.captures,
+            to_move: self.to_move,
+            passes: self.passes,
+        };
+        let captured = self.board.play(mov)?;
+
+        self.history.push(snapshot);
+        self.moves.push(mov);
+        self.captures[mov.color as usize] += captured.len() as u32;
+        self.to_move = mov.color.opponent();
+        if mov.vertex == Vertex::Pass {
+            self.passes += 1;
+        } else {
+            self.passes = 0;
+        }
+        Ok(captured)
+    }
+
+    /// Takes back the last move, returning it.
+    pub fn undo(&mut self) -> Option<Move> {
+        let snapshot = self.history.pop()?;
+        self.board = snapshot.board;
+        self.captures = snapshot.captures;
+        self.to_move = snapshot.to_move;
+        self.passes = snapshot.passes;
+        self.moves.pop()
+    }
+
+    /// Places handicap stones on the given vertices. White moves next.
+    pub fn set_handicap(&mut self, vertices: &[Vertex]) -> Result<(), HandicapError> {
+        if !self.board.is_empty() || !self.moves.is_empty() {
+            return Err(HandicapError::BoardNotEmpty);
+        }
+        if vertices.len() < 2 || vertices.len() >= self.size().area() {
+            return Err(HandicapError::InvalidNumberOfStones);
+        }
+        let mut board = self.board.clone();
+        for (i, v) in vertices.iter().enumerate() {
+            if vertices[..i].contains(v) || !v.is_on_board(self.size()) {
+                return Err(HandicapError::BadVertexList);
+            }
+            board.set(*v, Some(Color::Black), Color::White)
+                .map_err(|_| HandicapError::BadVertexList)?;
+        }
+
+        self.board = board;
+        self.handicap = vertices.to_vec();
+        self.to_move = Color::White;
+        Ok(())
+    }
+
+    /// Places setup stones of either colour, as SGF `AB` and `AW` do.
+    /// Only allowed before the first move.
+    pub fn add_setup(&mut self, stones: &[Move]) -> Result<(), HandicapError> {
+        if !self.moves.is_empty() {
+            return Err(HandicapError::BoardNotEmpty);
+        }
+        let mut board = self.board.clone();
+        for stone in stones {
+            if !stone.vertex.is_on_board(self.size()) || board.get(stone.vertex).is_some() {
+                return Err(HandicapError::BadVertexList);
+            }
+            board.set(stone.vertex, Some(stone.color), self.to_move)
+                .map_err(|_| HandicapError::BadVertexList)?;
+        }
+
+        self.board = board;
+        self.setup.extend_from_slice(stones);
+        Ok(())
+    }
+
+    /// Places handicap stones at the fixed positions of the GTP specification.
+    pub fn place_fixed_handicap(&mut self, stones: u32) -> Result<Vec<Vertex>, HandicapError> {
+        let vertices = fixed_handicap_vertices(stones, self.size())
+            .ok_or(HandicapError::InvalidNumberOfStones)?;
+        self.set_handicap(&vertices)?;
+        Ok(vertices)
+    }
+
+    /// Follows the engine after it successfully answered `command` with `data`.
+    /// Returns `false` when the game can no longer mirror the engine's position.
+    pub fn apply(&mut self, command: &GtpCommand, data: Option<&ResponseData>) -> bool {
+        match command {
+            GtpCommand::Play(m) => self.play(*m).is_ok(),
+            GtpCommand::Genmove(color) |
+            GtpCommand::LzGenmoveAnalyze(crate::model::AnalyzeArgs { color: Some(color), .. }) |
+            GtpCommand::KataGenmoveAnalyze(crate::model::AnalyzeArgs { color: Some(color), .. }) |
+            GtpCommand::GenmoveAnalyze(crate::model::AnalyzeArgs { color: Some(color), .. }) => {
+                match data.and_then(|d| d.as_vertex()) {
+                    Some(v) => self.play(Move { color: *color, vertex: *v }).is_ok(),
+                    None => false,
+                }
+            },
+            GtpCommand::Undo => self.undo().is_some(),
+            GtpCommand::ClearBoard => {
+                self.clear();
+                true
+            },
+            GtpCommand::Boardsize(size) => self.resize(RectSize::square(*size)),
+            GtpCommand::RectangularBoardsize(size) => self.resize(*size),
+            GtpCommand::Komi(komi) => {
+                self.komi = *komi;
+                true
+            },
+            GtpCommand::FixedHandicap(_) |
+            GtpCommand::PlaceFreeHandicap(_) => match data.and_then(|d| d.as_list_vertex()) {
+                Some(vertices) => self.set_handicap(vertices.inner()).is_ok(),
+                None => false,
+            },
+            GtpCommand::SetFreeHandicap(vertices) => self.set_handicap(vertices.inner()).is_ok(),
+            GtpCommand::Loadsgf { .. } |
+            GtpCommand::SetPosition(_) => false,
+            _ => true,
+        }
+    }
+
+    /// The commands reconstructing this position on an engine.
+    pub fn to_commands(&self) -> Vec<Command> {
+        self.commands(false)
+    }
+
+    /// Like [`Game::to_commands`], but places handicap and setup stones with
+    /// a single `set_position`, for engines supporting it.
+    pub fn to_commands_with_set_position(&self) -> Vec<Command> {
+        self.commands(true)
+    }
+
+    fn commands(&self, set_position: bool) -> Vec<Command> {
+        let size = self.size();
+        let mut commands = vec![
+            if size.width() == size.height() {
+                GtpCommand::Boardsize(size.width())
+            } else {
+                GtpCommand::RectangularBoardsize(size)
+            },
+            GtpCommand::ClearBoard,
+            GtpCommand::Komi(self.komi),
+        ];
+        if set_position && !(self.handicap.is_empty() && self.setup.is_empty()) {
+            let stones = self.handicap.iter()
+                .map(|v| Move { color: Color::Black, vertex: *v })
+                .chain(self.setup.iter().copied());
+            commands.push(GtpCommand::SetPosition(stones.collect()));
+            commands.extend(self.moves.iter().map(|m| GtpCommand::Play(*m)));
+        } else {
+            if !self.handicap.is_empty() {
+                commands.push(GtpCommand::SetFreeHandicap(self.handicap.iter().copied().collect::<List<Vertex>>()));
+            }
+            // GTP has no setup command, engines accept consecutive moves of one colour
+            commands.extend(self.setup.iter().chain(&self.moves).map(|m| GtpCommand::Play(*m)));
+        }
+
+        commands.into_iter().map(Command::from).collect()
+    }
+}
@@ -0,0 +1,477 @@
+use crate::model::ParseErrorKind;
+use crate::model::Args;
+use crate::model::Command;
+use crate::model::CommandName;
+use crate::model::ParseError;
+use crate::model::RectSize;
+use crate::model::TimeControl;
+use crate::model::types::*;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::fmt::Display;
+use core::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::{ Deserialize, Serialize };
+
+/// Argument of `final_status_list`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StoneStatus {
+    Alive,
+    Seki,
+    Dead,
+}
+
+impl Display for StoneStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Alive => write!(f, "alive"),
+            Self::Seki => write!(f, "seki"),
+            Self::Dead => write!(f, "dead"),
+        }
+    }
+}
+
+impl FromStr for StoneStatus {
+    type Err = ParseError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        match str.to_lowercase().as_str() {
+            "alive" => Ok(Self::Alive),
+            "seki" => Ok(Self::Seki),
+            "dead" => Ok(Self::Dead),
+            _ => Err(ParseError::new(ParseErrorKind::WrongAlternative).expected("alive, seki or dead").with_input(str)),
+        }
+    }
+}
+
+/// Arguments shared by the `analyze` family of commands:
+/// `[color] [interval] [key value...]`.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AnalyzeArgs {
+    pub color: Option<Color>,
+    pub interval: Option<u32>,
+    /// The interval follows the `interval` keyword, as KataGo documents it.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub interval_keyword: bool,
+    pub extra: List<String>,
+}
+
+impl Display for AnalyzeArgs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parts: Vec<String> = Vec::new();
+        if let Some(color) = self.color {
+            parts.push(color.to_string());
+        }
+        if let Some(interval) = self.interval {
+            if self.interval_keyword {
+                parts.push("interval".to_string());
+            }
+            parts.push(interval.to_string());
+        }
+        if !self.extra.inner().is_empty() {
+            parts.push(self.extra.to_string());
+        }
+        write!(f, "{}", parts.join(" "))
+    }
+}
+
+impl FromStr for AnalyzeArgs {
+    type Err = ParseError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let mut tokens = str.split_ascii_whitespace().peekable();
+        let mut args = Self::default();
+
+        if let Some(color) = tokens.peek().and_then(|t| t.parse::<Color>().ok()) {
+            args.color = Some(color);
+            tokens.next();
+        }
+        if let Some(interval) = tokens.peek().and_then(|t| t.parse::<u32>().ok()) {
+            args.interval = Some(interval);
+            tokens.next();
+        } else if tokens.peek() == Some(&"interval") {
+            tokens.next();
+            match tokens.next() {
+                Some(interval) => args.interval = Some(interval.parse()?),
+                None => return Err(ParseErrorKind::WrongArgs.into()),
+            }
+            args.interval_keyword = true;
+        }
+        args.extra = tokens.map(|t| t.to_string()).collect();
+
+        Ok(args)
+    }
+}
+
+impl AnalyzeArgs {
+    pub fn new(color: Option<Color>, interval: Option<u32>) -> Self {
+        Self {
+            color,
+            interval,
+            interval_keyword: false,
+            extra: List::new(),
+        }
+    }
+}
+
+/// A command with arguments typed according to its name.
+///
+/// Unlike [`Command`], which keeps whatever arguments appeared on the wire,
+/// a `GtpCommand` can only hold arguments that are valid for its command.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum GtpCommand {
+    ProtocolVersion,
+    Name,
+    Version,
+    KnownCommand(String),
+    ListCommands,
+    Quit,
+    Boardsize(u32),
+    ClearBoard,
+    Komi(f32),
+    FixedHandicap(u32),
+    PlaceFreeHandicap(u32),
+    SetFreeHandicap(List<Vertex>),
+    Play(Move),
+    Genmove(Color),
+    Undo,
+    TimeSettings { main: u32, byo_yomi: u32, stones: u32 },
+    TimeLeft { color: Color, time: u32, stones: u32 },
+    FinalScore,
+    FinalStatusList(StoneStatus),
+    Loadsgf { path: String, move_number: Option<u32> },
+    RegGenmove(Color),
+    Showboard,
+    RectangularBoardsize(RectSize),
+    SetPosition(List<Move>),
+    ClearCache,
+    Stop,
+    KataGetRules,
+    KataSetRules(String),
+    KataSetRule { name: String, value: String },
+    KgsRules(String),
+    KgsTimeSettings(TimeControl),
+    LzAnalyze(AnalyzeArgs),
+    KataAnalyze(AnalyzeArgs),
+    LzGenmoveAnalyze(AnalyzeArgs),
+    KataGenmoveAnalyze(AnalyzeArgs),
+    Analyze(AnalyzeArgs),
+    GenmoveAnalyze(AnalyzeArgs),
+    KataRawNn(String),
+    KataGetParam(String),
+    Cputime,
+    GomillCputime,
+    GetKomi,
+    KataListTimeSettings,
+    KataSetParam { name: String, value: String },
+}
+
+fn one<T>(args: &[&str]) -> Result<T, ParseError>
+where T: FromStr, ParseError: From<T::Err> {
+    match args {
+        [arg] => arg.parse().map_err(|e| ParseError::from(e).with_input(arg)),
+        _ => Err(ParseErrorKind::WrongArgs.into()),
+    }
+}
+
+fn three_u32(args: &[&str]) -> Result<(u32, u32, u32), ParseError> {
+    match args {
+        [a, b, c] => Ok((one(&[a])?, one(&[b])?, one(&[c])?)),
+        _ => Err(ParseErrorKind::WrongArgs.into()),
+    }
+}
+
+fn key_value(args: &[&str]) -> Result<(String, String), ParseError> {
+    match args {
+        [name, value @ ..] if !value.is_empty() => Ok((name.to_string(), value.join(" "))),
+        _ => Err(ParseErrorKind::WrongArgs.into()),
+    }
+}
+
+fn none(args: &[&str]) -> Result<(), ParseError> {
+    if args.is_empty() {
+        Ok(())
+    } else {
+        Err(ParseErrorKind::WrongArgs.into())
+    }
+}
+
+impl GtpCommand {
+    /// Parses the arguments of a command given its name.
+    pub fn from_parts(name: CommandName, args: &str) -> Result<Self, ParseError> {
+        let tokens: Vec<&str> = args.split_ascii_whitespace().collect();
+        let args = tokens.as_slice();
+
+        Ok(match name {
+            CommandName::ProtocolVersion => { none(args)?; Self::ProtocolVersion },
+            CommandName::Name => { none(args)?; Self::Name },
+            CommandName::Version => { none(args)?; Self::Version },
+            CommandName::KnownCommand => Self::KnownCommand(one(args)?),
+            CommandName::ListCommands => { none(args)?; Self::ListCommands },
+            CommandName::Quit => { none(args)?; Self::Quit },
+            CommandName::Boardsize => Self::Boardsize(one(args)?),
+            CommandName::ClearBoard => { none(args)?; Self::ClearBoard },
+            CommandName::Komi => Self::Komi(one(args)?),
+            CommandName::FixedHandicap => Self::FixedHandicap(one(args)?),
+            CommandName::PlaceFreeHandicap => Self::PlaceFreeHandicap(one(args)?),
+            CommandName::SetFreeHandicap => {
+                let vertices = args.iter()
+                    .map(|v| v.parse())
+                    .collect::<Result<List<Vertex>, ParseError>>()?;
+                if vertices.inner().is_empty() {
+                    return Err(ParseErrorKind::WrongArgs.into());
+                }
+                Self::SetFreeHandicap(vertices)
+            },
+            CommandName::Play => match args {
+                [color, vertex] => Self::Play(Move {
+                    color: color.parse()?,
+                    vertex: vertex.parse()?,
+                }),
+                _ => return Err(ParseErrorKind::WrongArgs.into()),
+            },
+            CommandName::Genmove => Self::Genmove(one(args)?),
+            CommandName::Undo => { none(args)?; Self::Undo },
+            CommandName::TimeSettings => {
+                let (main, byo_yomi, stones) = three_u32(args)?;
+                Self::TimeSettings { main, byo_yomi, stones }
+            },
+            CommandName::TimeLeft => match args {
+                [color, time, stones] => Self::TimeLeft {
+                    color: color.parse()?,
+                    time: time.parse()?,
+                    stones: stones.parse()?,
+                },
+                _ => return Err(ParseErrorKind::WrongArgs.into()),
+            },
+            CommandName::FinalScore => { none(args)?; Self::FinalScore },
+            CommandName::FinalStatusList => Self::FinalStatusList(one(args)?),
+            CommandName::Loadsgf => match args {
+                [path] => Self::Loadsgf { path: path.to_string(), move_number: None },
+                [path, move_number] => Self::Loadsgf {
+                    path: path.to_string(),
+                    move_number: Some(move_number.parse()?),
+                },
+                _ => return Err(ParseErrorKind::WrongArgs.into()),
+            },
+            CommandName::RegGenmove => Self::RegGenmove(one(args)?),
+            CommandName::Showboard => { none(args)?; Self::Showboard },
+            CommandName::RectangularBoardsize => match args {
+                [width, height] => Self::RectangularBoardsize(RectSize::new(width.parse()?, height.parse()?)),
+                _ => return Err(ParseErrorKind::WrongArgs.into()),
+            },
+            CommandName::SetPosition => Self::SetPosition(super::command::parse_moves(args)?),
+            CommandName::ClearCache => { none(args)?; Self::ClearCache },
+            CommandName::Stop => { none(args)?; Self::Stop },
+            CommandName::KataGetRules => { none(args)?; Self::KataGetRules },
+            CommandName::KataSetRules => Self::KataSetRules(one(args)?),
+            CommandName::KataSetRule => {
+                let (name, value) = key_value(args)?;
+                Self::KataSetRule { name, value }
+            },
+            CommandName::KgsRules => Self::KgsRules(one(args)?),
+            CommandName::KgsTimeSettings => Self::KgsTimeSettings(args.join(" ").parse()?),
+            CommandName::LzAnalyze => Self::LzAnalyze(args.join(" ").parse()?),
+            CommandName::KataAnalyze => Self::KataAnalyze(args.join(" ").parse()?),
+            CommandName::LzGenmoveAnalyze => Self::LzGenmoveAnalyze(args.join(" ").parse()?),
+            CommandName::KataGenmoveAnalyze => Self::KataGenmoveAnalyze(args.join(" ").parse()?),
+            CommandName::Analyze => Self::Analyze(args.join(" ").parse()?),
+            CommandName::GenmoveAnalyze => Self::GenmoveAnalyze(args.join(" ").parse()?),
+            CommandName::KataRawNn => Self::KataRawNn(one(args)?),
+            CommandName::KataGetParam => Self::KataGetParam(one(args)?),
+            CommandName::Cputime => { none(args)?; Self::Cputime },
+            CommandName::GomillCputime => { none(args)?; Self::GomillCputime },
+            CommandName::GetKomi => { none(args)?; Self::GetKomi },
+            CommandName::KataListTimeSettings => { none(args)?; Self::KataListTimeSettings },
+            CommandName::KataSetParam => {
+                let (name, value) = key_value(args)?;
+                Self::KataSetParam { name, value }
+            },
+            CommandName::Unknown => return Err(ParseErrorKind::WrongCommandName.into()),
+        })
+    }
+
+    pub const fn name(&self) -> CommandName {
+        match self {
+            Self::ProtocolVersion => CommandName::ProtocolVersion,
+            Self::Name => CommandName::Name,
+            Self::Version => CommandName::Version,
+            Self::KnownCommand(_) => CommandName::KnownCommand,
+            Self::ListCommands => CommandName::ListCommands,
+            Self::Quit => CommandName::Quit,
+            Self::Boardsize(_) => CommandName::Boardsize,
+            Self::ClearBoard => CommandName::ClearBoard,
+            Self::Komi(_) => CommandName::Komi,
+            Self::FixedHandicap(_) => CommandName::FixedHandicap,
+            Self::PlaceFreeHandicap(_) => CommandName::PlaceFreeHandicap,
+            Self::SetFreeHandicap(_) => CommandName::SetFreeHandicap,
+            Self::Play(_) => CommandName::Play,
+            Self::Genmove(_) => CommandName::Genmove,
+            Self::Undo => CommandName::Undo,
+            Self::TimeSettings { .. } => CommandName::TimeSettings,
+            Self::TimeLeft { .. } => CommandName::TimeLeft,
+            Self::FinalScore => CommandName::FinalScore,
+            Self::FinalStatusList(_) => CommandName::FinalStatusList,
+            Self::Loadsgf { .. } => CommandName::Loadsgf,
+            Self::RegGenmove(_) => CommandName::RegGenmove,
+            Self::Showboard => CommandName::Showboard,
+            Self::RectangularBoardsize(_) => CommandName::RectangularBoardsize,
+            Self::SetPosition(_) => CommandName::SetPosition,
+            Self::ClearCache => CommandName::ClearCache,
+            Self::Stop => CommandName::Stop,
+            Self::KataGetRules => CommandName::KataGetRules,
+            Self::KataSetRules(_) => CommandName::KataSetRules,
+            Self::KataSetRule { .. } => CommandName::KataSetRule,
+            Self::KgsRules(_) => CommandName::KgsRules,
+            Self::KgsTimeSettings(_) => CommandName::KgsTimeSettings,
+            Self::LzAnalyze(_) => CommandName::LzAnalyze,
+            Self::KataAnalyze(_) => CommandName::KataAnalyze,
+            Self::LzGenmoveAnalyze(_) => CommandName::LzGenmoveAnalyze,
+            Self::KataGenmoveAnalyze(_) => CommandName::KataGenmoveAnalyze,
+            Self::Analyze(_) => CommandName::Analyze,
+            Self::GenmoveAnalyze(_) => CommandName::GenmoveAnalyze,
+            Self::KataRawNn(_) => CommandName::KataRawNn,
+            Self::KataGetParam(_) => CommandName::KataGetParam,
+            Self::Cputime => CommandName::Cputime,
+            Self::GomillCputime => CommandName::GomillCputime,
+            Self::GetKomi => CommandName::GetKomi,
+            Self::KataListTimeSettings => CommandName::KataListTimeSettings,
+            Self::KataSetParam { .. } => CommandName::KataSetParam,
+        }
+    }
+
+    /// Wire arguments, in the same shape `Command::from_str` produces them.
+    pub fn args(&self) -> Option<Args> {
+        let strings = |v: Vec<String>| Some(Args::list_string(v.into_iter().collect()));
+
+        match self {
+            Self::ProtocolVersion |
+            Self::Name |
+            Self::Version |
+            Self::ListCommands |
+            Self::Quit |
+            Self::ClearBoard |
+            Self::Undo |
+            Self::FinalScore |
+            Self::Showboard |
+            Self::ClearCache |
+            Self::Stop |
+            Self::KataGetRules |
+            Self::Cputime |
+            Self::GomillCputime |
+            Self::GetKomi |
+            Self::KataListTimeSettings => None,
+            Self::KnownCommand(name) => Some(Args::string(name.clone())),
+            Self::FinalStatusList(status) => Some(Args::string(status.to_string())),
+            Self::Boardsize(i) |
+            Self::FixedHandicap(i) |
+            Self::PlaceFreeHandicap(i) => Some(Args::int(*i)),
+            Self::Komi(komi) => Some(Args::float(*komi)),
+            Self::SetFreeHandicap(vertices) => Some(Args::list_vertex(vertices.clone())),
+            Self::Play(m) => Some(Args::entity((*m).into())),
+            Self::Genmove(c) |
+            Self::RegGenmove(c) => Some(Args::entity((*c).into())),
+            Self::TimeSettings { main, byo_yomi, stones } =>
+                strings(alloc::vec![main.to_string(), byo_yomi.to_string(), stones.to_string()]),
+            Self::TimeLeft { color, time, stones } =>
+                strings(alloc::vec![color.to_string(), time.to_string(), stones.to_string()]),
+            Self::Loadsgf { path, move_number } => {
+                let mut v = alloc::vec![path.clone()];
+                if let Some(n) = move_number {
+                    v.push(n.to_string());
+                }
+                strings(v)
+            },
+            Self::RectangularBoardsize(size) => Some(Args::kata_size(*size)),
+            Self::SetPosition(moves) => Some(Args::list_move(moves.clone())),
+            Self::KataSetRules(s) |
+            Self::KgsRules(s) |
+            Self::KataRawNn(s) |
+            Self::KataGetParam(s) => strings(alloc::vec![s.clone()]),
+            Self::KataSetRule { name, value } |
+            Self::KataSetParam { name, value } => strings(alloc::vec![name.clone(), value.clone()]),
+            Self::KgsTimeSettings(control) =>
+                strings(control.to_string().split_ascii_whitespace().map(|s| s.to_string()).collect()),
+            Self::LzAnalyze(a) |
+            Self::KataAnalyze(a) |
+            Self::LzGenmoveAnalyze(a) |
+            Self::KataGenmoveAnalyze(a) |
+            Self::Analyze(a) |
+            Self::GenmoveAnalyze(a) => {
+                let s = a.to_string();
+                if s.is_empty() {
+                    None
+                } else {
+                    strings(s.split_ascii_whitespace().map(|s| s.to_string()).collect())
+                }
+            },
+        }
+    }
+
+    pub fn with_id(self, id: u32) -> Command {
+        let mut command: Command = self.into();
+        *command.id_mut() = Some(id);
+        command
+    }
+}
+
+impl From<GtpCommand> for Command {
+    fn from(command: GtpCommand) -> Self {
+        Command::new(command.name(), command.args())
+    }
+}
+
+impl From<&GtpCommand> for Command {
+    fn from(command: &GtpCommand) -> Self {
+        Command::new(command.name(), command.args())
+    }
+}
+
+impl TryFrom<&Command> for GtpCommand {
+    type Error = ParseError;
+
+    fn try_from(command: &Command) -> Result<Self, Self::Error> {
+        match command.args() {
+            Some(args) => Self::from_parts(command.name(), &args.to_string()),
+            None => Self::from_parts(command.name(), ""),
+        }
+    }
+}
+
+impl TryFrom<Command> for GtpCommand {
+    type Error = ParseError;
+
+    fn try_from(command: Command) -> Result<Self, Self::Error> {
+        Self::try_from(&command)
+    }
+}
+
+impl FromStr for GtpCommand {
+    type Err = ParseError;
+
+    /// Parses the arguments directly, as [`Command`] cannot hold those of
+    /// every command. An id is ignored.
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let str = crate::model::preprocess(str);
+        let mut tokens = str.split_ascii_whitespace().peekable();
+        if tokens.peek().is_none() {
+            return Err(ParseErrorKind::EmptyString.into());
+        }
+        tokens.next_if(|t| t.parse::<u32>().is_ok());
+        let name = tokens.next().ok_or(ParseErrorKind::WrongCommandName)?;
+        let args: Vec<&str> = tokens.collect();
+        Self::from_parts(name.parse()?, &args.join(" "))
+    }
+}
+
+impl Display for GtpCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", Command::from(self))
+    }
+}
@@ -1,19 +1,160 @@
-use std::str::FromStr;
+use crate::model::ParseErrorKind;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Display;
+use core::str::FromStr;
 
+use crate::model::Color;
+use crate::model::OwnershipMap;
+use crate::model::RectSize;
 use crate::model::Vertex;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+/// Point of view of winrates, scores and ownership, which KataGo sets with
+/// `reportAnalysisWinratesAs`. Leela Zero reports for the side to move.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Perspective {
+    #[default]
+    SideToMove,
+    Black,
+    White,
+}
+
+impl Perspective {
+    /// The player whose point of view it is.
+    pub const fn color(&self, to_move: Color) -> Color {
+        match self {
+            Self::SideToMove => to_move,
+            Self::Black => Color::Black,
+            Self::White => Color::White,
+        }
+    }
+}
+
+/// Analysis of a position, as given by `kata-analyze` and `lz-analyze`.
+///
+/// The helpers comparing moves expect values from the side to move's point
+/// of view, see [`with_perspective`](Self::with_perspective).
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default)]
 pub struct Info {
     pub explored_moves: Vec<InfoMove>,
-    pub ownership: Vec<f32>,
+    /// From the engine's point of view as parsed, from Black's once
+    /// converted with [`ownership_from`](Self::ownership_from).
+    pub ownership: Option<OwnershipMap>,
 }
 
 impl Info {
     pub const fn new() -> Self {
         Self {
             explored_moves: vec![],
-            ownership: vec![],
+            ownership: None,
+        }
+    }
+
+    /// Sets the board size of the ownership maps, which info lines do not
+    /// give. Maps with a different number of values are left unchanged.
+    pub fn with_board_size(mut self, size: RectSize) -> Self {
+        fn resize(map: &mut Option<OwnershipMap>, size: RectSize) {
+            if let Some(m) = map.take() {
+                *map = Some(m.with_size(size).unwrap_or_else(|m| m));
+            }
+        }
+        resize(&mut self.ownership, size);
+        for info in &mut self.explored_moves {
+            resize(&mut info.ownership, size);
+        }
+        self
+    }
+
+    /// Converts winrates, scores and utilities reported from the point of
+    /// view `from` to the point of view `to`, `to_move` being the player to
+    /// move in the position. Ownership is left to
+    /// [`ownership_from`](Self::ownership_from).
+    pub fn with_perspective(mut self, from: Perspective, to: Perspective, to_move: Color) -> Self {
+        if from.color(to_move) != to.color(to_move) {
+            for info in &mut self.explored_moves {
+                info.flip();
+            }
         }
+        self
+    }
+
+    /// Brings ownership maps reported from the engine's point of view
+    /// `engine` to Black's, which [`OwnershipMap`] expects. To be called
+    /// once, on an info as parsed.
+    pub fn ownership_from(mut self, engine: Perspective, to_move: Color) -> Self {
+        if engine.color(to_move) == Color::Black {
+            return self;
+        }
+        let maps = self.explored_moves.iter_mut().map(|info| &mut info.ownership);
+        for ownership in maps.chain([&mut self.ownership]).flatten() {
+            ownership.flip();
+        }
+        self
+    }
+
+    /// The engine's preferred move, the one with the lowest `order`.
+    pub fn best_move(&self) -> Option<&InfoMove> {
+        self.explored_moves.iter().min_by_key(|m| m.order)
+    }
+
+    /// The moves from best to worst.
+    pub fn ranked_moves(&self) -> Vec<&InfoMove> {
+        let mut moves: Vec<&InfoMove> = self.explored_moves.iter().collect();
+        moves.sort_by_key(|m| m.order);
+        moves
+    }
+
+    pub fn get(&self, vertex: Vertex) -> Option<&InfoMove> {
+        self.explored_moves.iter().find(|m| m.coord == vertex)
+    }
+
+    /// The moves searched at least `min_visits` times, from best to worst.
+    pub fn moves_with_visits(&self, min_visits: u64) -> Vec<&InfoMove> {
+        self.ranked_moves().into_iter().filter(|m| m.visits >= min_visits).collect()
+    }
+
+    pub fn total_visits(&self) -> u64 {
+        self.explored_moves.iter().map(|m| m.visits).sum()
+    }
+
+    /// Share of the visits each move got, a policy sharper than the prior.
+    pub fn visit_shares(&self) -> Vec<(Vertex, f32)> {
+        let total = self.total_visits();
+        self.ranked_moves().into_iter()
+            .map(|m| (m.coord, if total == 0 { 0.0 } else { m.visits as f32 / total as f32 }))
+            .collect()
+    }
+
+    /// Points lost by playing `vertex` instead of the best move, according
+    /// to `scoreLead`. `None` if `vertex` was not searched.
+    pub fn point_loss(&self, vertex: Vertex) -> Option<f32> {
+        Some(self.best_move()?.score_lead - self.get(vertex)?.score_lead)
+    }
+
+    /// Winrate lost by playing `vertex` instead of the best move.
+    pub fn winrate_loss(&self, vertex: Vertex) -> Option<f32> {
+        Some(self.best_move()?.winrate - self.get(vertex)?.winrate)
+    }
+}
+
+/// Writes the moves as a `kata-analyze` info line.
+impl Display for Info {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, info) in self.explored_moves.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "info {}", info)?;
+        }
+        if let Some(ownership) = &self.ownership {
+            write!(f, " ownership {}", ownership)?;
+        }
+        Ok(())
     }
 }
 
@@ -21,38 +162,36 @@ impl FromStr for Info {
     type Err = super::ParseError;
     
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let matches = if s.contains("ownership ") {
-            s.split_once("ownership ").unwrap()
-        } else {
-            (s, "")
-        };
+        let matches = s.split_once("ownership ").unwrap_or((s, ""));
         if matches.0.is_empty() {
-            return Err(super::ParseError::EmptyString)
+            return Err(ParseErrorKind::EmptyString.into())
         }
         let infos = matches.0; //info part
-        let ownership = matches.1; //ownership part
+        if !infos.trim_start().starts_with("info") {
+            return Err(super::ParseError::new(ParseErrorKind::WrongAnswerFormat).expected("info").with_input(s));
+        }
+        let ownership = matches.1.split("ownershipStdev").next().unwrap_or(""); //ownership part
         
         let mut matches = infos.split("info");
         matches.next(); //consume empty string
         
         let mut infos: Vec<InfoMove> = Vec::new();
-        while let Some(s) = matches.next() {
-            infos.push(s.parse()?);
+        for info in matches {
+            infos.push(info.parse().map_err(|e: super::ParseError| e.at_token(s, info))?);
         }
 
-        let mut vec: Vec<f32> = vec![];
-        if !ownership.is_empty() {
-            let matches = ownership.split_ascii_whitespace();
-            vec = matches.map(|f| f.parse().unwrap()).collect();
-        }
+        let vec: Vec<f32> = ownership.split_ascii_whitespace()
+            .map(|f| f.parse().map_err(|e| super::ParseError::from(e).at_token(s, f)))
+            .collect::<Result<_, _>>()?;
 
         Ok(Self {
             explored_moves: infos,
-            ownership: vec,
+            ownership: OwnershipMap::from_values(vec),
         })
     }
 }
 
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[derive(Debug, Clone)]
 pub struct InfoMove {
     pub coord: Vertex,
@@ -70,6 +209,55 @@ pub struct InfoMove {
     pub pv: Vec<Vertex>,
     pub pv_visits: Vec<u64>,
     pub weight: u64,
+    /// Ownership after this move, given by `kata-analyze ... movesOwnership true`.
+    pub ownership: Option<OwnershipMap>,
+}
+
+/// Keys which may follow the principal variation.
+const AFTER_PV: &[&str] = &["pvVisits", "pvEdgeVisits", "movesOwnership", "movesOwnershipStdev"];
+
+/// Parses the value following `key` in `line`, recording where it failed.
+fn value<T>(line: &str, key: &'static str, value: Option<&str>) -> Result<T, super::ParseError>
+where T: FromStr, super::ParseError: From<T::Err> {
+    match value {
+        Some(v) => v.parse::<T>()
+            .map_err(|e| super::ParseError::from(e).expected(key).at_token(line, v)),
+        None => Err(super::ParseError::new(ParseErrorKind::WrongArgs).expected(key).with_input(line)),
+    }
+}
+
+impl InfoMove {
+    /// Swaps Black's and White's point of view of the values, not of the ownership.
+    fn flip(&mut self) {
+        self.winrate = 1.0 - self.winrate;
+        self.lcb = 1.0 - self.lcb;
+        self.score_mean = -self.score_mean;
+        self.score_lead = -self.score_lead;
+        self.score_selfplay = -self.score_selfplay;
+        self.utility = -self.utility;
+        self.utility_lcb = -self.utility_lcb;
+    }
+}
+
+impl Display for InfoMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} visits {} utility {} winrate {} scoreMean {} scoreStdev {} scoreLead {} scoreSelfplay {} prior {} lcb {} utilityLcb {} weight {} order {} pv",
+            self.coord, self.visits, self.utility, self.winrate, self.score_mean, self.score_stdev, self.score_lead,
+            self.score_selfplay, self.prior, self.lcb, self.utility_lcb, self.weight, self.order)?;
+        for vertex in &self.pv {
+            write!(f, " {}", vertex)?;
+        }
+        if !self.pv_visits.is_empty() {
+            write!(f, " pvVisits")?;
+            for visits in &self.pv_visits {
+                write!(f, " {}", visits)?;
+            }
+        }
+        if let Some(ownership) = &self.ownership {
+            write!(f, " movesOwnership {}", ownership)?;
+        }
+        Ok(())
+    }
 }
 
 impl FromStr for InfoMove {
@@ -91,66 +279,75 @@ impl FromStr for InfoMove {
         let mut pv: Vec<Vertex> = vec![];
         let mut pv_visits: Vec<u64> = vec![];
         let mut weight: u64 = 0;
+        let mut ownership: Vec<f32> = vec![];
         
         let matches = s.split_whitespace();
         let mut matches = matches.peekable();
 
-        while let Some(s) = matches.next() {
-            match s {
+        while let Some(key) = matches.next() {
+            match key {
                 "move" => {
-                    coord = matches.next().unwrap().parse()?;
+                    coord = value(s, "move", matches.next())?;
                 },
                 "visits" => {
-                    visits = matches.next().unwrap().parse()?;
+                    visits = value(s, "visits", matches.next())?;
                 },
                 "winrate" => {
-                    winrate = matches.next().unwrap().parse()?;
+                    winrate = value(s, "winrate", matches.next())?;
                 },
                 "scoreMean" => {
-                    score_mean = matches.next().unwrap().parse()?;
+                    score_mean = value(s, "scoreMean", matches.next())?;
                 },
                 "scoreStdev" => {
-                    score_stdev = matches.next().unwrap().parse()?;
+                    score_stdev = value(s, "scoreStdev", matches.next())?;
                 },
                 "scoreLead" => {
-                    score_lead = matches.next().unwrap().parse()?;
+                    score_lead = value(s, "scoreLead", matches.next())?;
                 },
                 "scoreSelfplay" => {
-                    score_selfplay = matches.next().unwrap().parse()?;
+                    score_selfplay = value(s, "scoreSelfplay", matches.next())?;
                 },
                 "prior" => {
-                    prior = matches.next().unwrap().parse()?;
+                    prior = value(s, "prior", matches.next())?;
                 },
                 "utility" => {
-                    utility = matches.next().unwrap().parse()?;
+                    utility = value(s, "utility", matches.next())?;
                 },
                 "lcb" => {
-                    lcb = matches.next().unwrap().parse()?;
+                    lcb = value(s, "lcb", matches.next())?;
                 },
                 "utilityLcb" => {
-                    utility_lcb = matches.next().unwrap().parse()?;
+                    utility_lcb = value(s, "utilityLcb", matches.next())?;
                 },
                 "order" => {
-                    order = matches.next().unwrap().parse()?;
+                    order = value(s, "order", matches.next())?;
                 },
                 "pv" => {
-                    while let Some(s) = matches.peek() {
-                        if *s == "pvVisits" {
-                            break
-                        }
-                        pv.push(matches.next().unwrap().parse()?);
+                    while let Some(v) = matches.next_if(|v| !AFTER_PV.contains(v)) {
+                        pv.push(value(s, "pv", Some(v))?);
                     }
                 },
                 "pvVisits" => {
-                    while let Some(s) = matches.next() {
-                        pv_visits.push(s.parse()?);
+                    while let Some(v) = matches.next_if(|v| !AFTER_PV.contains(v)) {
+                        pv_visits.push(value(s, "pvVisits", Some(v))?);
                     }
                 },
+                "movesOwnership" => {
+                    while let Some(v) = matches.next_if(|v| !AFTER_PV.contains(v)) {
+                        ownership.push(value(s, "movesOwnership", Some(v))?);
+                    }
+                },
+                "pvEdgeVisits" | "movesOwnershipStdev" => {
+                    while matches.next_if(|v| !AFTER_PV.contains(v)).is_some() {}
+                },
                 "isSymmetryOf" => { matches.next();},
                 "weight" => {
-                    weight = matches.next().unwrap().parse()?;
+                    weight = value(s, "weight", matches.next())?;
                 },
-                s @ _ => log::warn!("\"{}\" not yet handled", s),
+                #[cfg(feature = "log")]
+                key => log::warn!("\"{}\" not yet handled", key),
+                #[cfg(not(feature = "log"))]
+                _ => {},
             }
         }
 
@@ -171,6 +368,7 @@ impl FromStr for InfoMove {
             pv,
             pv_visits,
             weight,
+            ownership: OwnershipMap::from_values(ownership),
         })
     }
 }
@@ -1,18 +1,35 @@
+mod board;
 mod command;
+mod error;
 mod failure;
+mod game;
+mod gtp_command;
 mod info;
+mod ownership;
+mod reader;
 mod response;
+mod scoring;
+mod time;
 mod types;
+use alloc::string::String;
 use core::str::FromStr;
 use core::fmt::Debug;
 use core::fmt::Display;
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
 
+pub use board::*;
 pub use command::*;
+pub use error::*;
 pub use failure::*;
+pub use game::*;
+pub use gtp_command::*;
 pub use info::*;
+pub use ownership::*;
+pub use reader::*;
 pub use response::*;
+pub use scoring::*;
+pub use time::*;
 pub use types::*;
 
 
@@ -24,38 +41,53 @@ pub enum Answer {
     Response(Response),
 }
 
+impl Display for Answer {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Failure(failure) => write!(f, "{}", failure),
+            Self::Info(info) => writeln!(f, "{}", info),
+            Self::Response(response) => write!(f, "{}", response),
+        }
+    }
+}
+
 impl Answer {
     pub fn parse_answer(answer: &str) -> Result<Answer, ParseError> {
         if let Ok(response) = answer.parse::<Response>() {
             Ok(Answer::Response(response))
-        } else if let Ok(info) = answer.parse::<Info>() {
-            Ok(Answer::Info(info))
         } else if let Ok(failure) = answer.parse::<Failure>() {
             Ok(Answer::Failure(failure))
+        } else if let Ok(info) = answer.parse::<Info>() {
+            Ok(Answer::Info(info))
         } else {
-            Err(ParseError::WrongAnswerFormat)
+            Err(ParseErrorKind::WrongAnswerFormat.into())
         }
     }
 
-    pub const fn is_response(&self) -> bool {
-        match self {
-            Self::Response(_) => true,
-            _ => false,
+    /// Parses an answer to `command`. Responses are decoded with
+    /// [`Response::parse_for`] so their data matches the command's response type.
+    pub fn parse_for(command: CommandName, answer: &str) -> Result<Answer, ParseError> {
+        if answer.starts_with('=') {
+            Ok(Answer::Response(Response::parse_for(command, answer)?))
+        } else if answer.starts_with('?') {
+            Ok(Answer::Failure(answer.parse()?))
+        } else if let Ok(info) = answer.parse::<Info>() {
+            Ok(Answer::Info(info))
+        } else {
+            Err(ParseErrorKind::WrongAnswerFormat.into())
         }
     }
 
+    pub const fn is_response(&self) -> bool {
+        matches!(self, Self::Response(_))
+    }
+
     pub const fn is_failure(&self) -> bool {
-        match self {
-            Self::Failure(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::Failure(_))
     }
 
     pub const fn is_info(&self) -> bool {
-        match self {
-            Self::Info(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::Info(_))
     }
 
     pub fn to_response(self) -> Result<Response, Self> {
@@ -81,35 +113,6 @@ impl Answer {
 }
 
 
-#[derive(Debug, Clone, Copy)]
-pub enum ParseError {
-    WrongAlternative,
-    WrongColor,
-    WrongCommandName,
-    WrongCoordinates,
-    WrongSimpleEntity,
-    WrongArgs,
-    WrongBool,
-    WrongResponseData,
-    WrongResponseFormat,
-    WrongFailureFormat,
-    WrongAnswerFormat,
-    WrongScore,
-    EmptyString,
-}
-
-impl From<core::num::ParseIntError> for ParseError {
-    fn from(_err: core::num::ParseIntError) -> Self {
-        Self::WrongArgs
-    }
-}
-
-impl From<core::num::ParseFloatError> for ParseError {
-    fn from(_err: core::num::ParseFloatError) -> Self {
-        Self::WrongArgs
-    }
-}
-
 pub trait Entity : Display + Debug + Clone + FromStr {
 
 }
@@ -0,0 +1,178 @@
+use crate::model::Board;
+use crate::model::Color;
+use crate::model::RectSize;
+use crate::model::Vertex;
+use crate::model::MAX_BOARD_SIZE;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Display;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Ownership estimated by an engine for every point, from -1 (White owns it)
+/// to 1 (Black owns it). Engines report it from the point of view they
+/// report winrates from, for the side to move by default with KataGo's GTP;
+/// [`Info::ownership_from`](crate::model::Info::ownership_from) brings
+/// parsed maps to Black's point of view.
+///
+/// Values are stored row-major from the top row, in the order of [`Vertex::to_index`].
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "UncheckedOwnershipMap"))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnershipMap {
+    size: RectSize,
+    values: Vec<f32>,
+}
+
+/// Deserialized fields, checked by [`OwnershipMap::new`].
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct UncheckedOwnershipMap {
+    size: RectSize,
+    values: Vec<f32>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<UncheckedOwnershipMap> for OwnershipMap {
+    type Error = &'static str;
+
+    fn try_from(map: UncheckedOwnershipMap) -> Result<Self, Self::Error> {
+        if !map.size.is_valid() {
+            return Err("invalid board size");
+        }
+        Self::new(map.size, map.values).ok_or("not one ownership value per point")
+    }
+}
+
+impl OwnershipMap {
+    /// Fails if there is not one value per point of `size`.
+    pub fn new(size: RectSize, values: Vec<f32>) -> Option<Self> {
+        if values.len() != size.area() {
+            return None;
+        }
+        Some(Self { size, values })
+    }
+
+    /// Values of an engine's info line, which does not give the board size.
+    /// It is guessed square, or the widest board up to [`MAX_BOARD_SIZE`]
+    /// with that many points; [`with_size`](Self::with_size) fixes it for
+    /// rectangular boards. Fails when no board has that many points.
+    pub fn from_values(values: Vec<f32>) -> Option<Self> {
+        let len = values.len();
+        let side = (1..=MAX_BOARD_SIZE).find(|n| (n * n) as usize == len);
+        let size = match side {
+            Some(side) => RectSize::square(side),
+            None => {
+                let width = (1..=MAX_BOARD_SIZE as usize).rev()
+                    .find(|w| len.is_multiple_of(*w) && (1..=MAX_BOARD_SIZE as usize).contains(&(len / w)))?;
+                RectSize::new(width as u32, (len / width) as u32)
+            },
+        };
+        Some(Self { size, values })
+    }
+
+    /// The same values on a board of `size`, if their number matches.
+    pub fn with_size(self, size: RectSize) -> Result<Self, Self> {
+        if self.values.len() != size.area() {
+            return Err(self);
+        }
+        Ok(Self { size, values: self.values })
+    }
+
+    pub const fn size(&self) -> RectSize {
+        self.size
+    }
+
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    pub fn get(&self, vertex: Vertex) -> Option<f32> {
+        vertex.to_index(self.size).map(|i| self.values[i])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Vertex, f32)> + '_ {
+        let size = self.size;
+        self.values.iter()
+            .enumerate()
+            .filter_map(move |(i, v)| Vertex::from_index(i, size).map(|vertex| (vertex, *v)))
+    }
+
+    /// Swaps Black's and White's point of view.
+    pub fn flip(&mut self) {
+        for value in &mut self.values {
+            *value = -*value;
+        }
+    }
+
+    /// Ownership of `vertex` from the point of view of `color`.
+    pub fn get_for(&self, vertex: Vertex, color: Color) -> Option<f32> {
+        self.get(vertex).map(|v| toward(v, color))
+    }
+
+    /// The player owning `vertex` with at least `threshold`, e.g. 0.5.
+    pub fn owner(&self, vertex: Vertex, threshold: f32) -> Option<Color> {
+        self.get(vertex).and_then(|v| owner(v, threshold))
+    }
+
+    /// Expected number of points, stones included, `color` ends up with.
+    pub fn area(&self, color: Color) -> f32 {
+        self.values.iter().map(|v| toward(*v, color).max(0.0)).sum()
+    }
+
+    /// Expected territory of `color`: the points of `board` not occupied by
+    /// its stones that it ends up owning, including captured dead stones.
+    pub fn territory(&self, color: Color, board: &Board) -> f32 {
+        self.iter()
+            .filter(|(vertex, _)| board.get(*vertex) != Some(color))
+            .map(|(_, v)| toward(v, color).max(0.0))
+            .sum()
+    }
+
+    /// Stones of `board` owned by the opponent with at least `threshold`.
+    pub fn dead_stones(&self, board: &Board, threshold: f32) -> Vec<Vertex> {
+        self.iter()
+            .filter(|(vertex, v)| match board.get(*vertex) {
+                Some(color) => toward(*v, color.opponent()) >= threshold,
+                None => false,
+            })
+            .map(|(vertex, _)| vertex)
+            .collect()
+    }
+
+    /// The owner of every point, in the order of the values, `None` for
+    /// points neither player owns with at least `threshold`.
+    pub fn territory_map(&self, threshold: f32) -> Vec<Option<Color>> {
+        self.values.iter().map(|v| owner(*v, threshold)).collect()
+    }
+}
+
+fn toward(value: f32, color: Color) -> f32 {
+    match color {
+        Color::Black => value,
+        Color::White => -value,
+    }
+}
+
+fn owner(value: f32, threshold: f32) -> Option<Color> {
+    if value > 0.0 && value >= threshold {
+        Some(Color::Black)
+    } else if value < 0.0 && -value >= threshold {
+        Some(Color::White)
+    } else {
+        None
+    }
+}
+
+/// Writes the values separated by spaces, as in info lines.
+impl Display for OwnershipMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, value) in self.values.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", value)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+use crate::model::Command;
+use crate::model::ParseError;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Applies the GTP input preprocessing rules to a single line:
+/// control characters other than HT and LF are removed, everything from a `#`
+/// onwards is discarded and HT is converted to a space.
+pub fn preprocess(line: &str) -> String {
+    let bytes: Vec<u8> = preprocess_bytes(line.as_bytes());
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn preprocess_bytes(line: &[u8]) -> Vec<u8> {
+    line.iter()
+        .take_while(|b| **b != b'#')
+        .filter(|b| **b == b'\t' || **b == b'\n' || !(b.is_ascii_control()))
+        .map(|b| if *b == b'\t' { b' ' } else { *b })
+        .collect()
+}
+
+/// Reads GTP commands from a raw byte stream, such as an engine's stdin.
+///
+/// Lines are split on LF, preprocessed as required by the protocol, and empty
+/// or comment-only lines are skipped.
+#[derive(Debug, Clone)]
+pub struct CommandReader<I> {
+    bytes: I,
+}
+
+impl<I: Iterator<Item = u8>> CommandReader<I> {
+    pub const fn new(bytes: I) -> Self {
+        Self {
+            bytes,
+        }
+    }
+
+    pub fn into_inner(self) -> I {
+        self.bytes
+    }
+
+    fn next_line(&mut self) -> Option<Vec<u8>> {
+        let mut line = Vec::new();
+        let mut read_any = false;
+        for b in self.bytes.by_ref() {
+            read_any = true;
+            if b == b'\n' {
+                return Some(line);
+            }
+            line.push(b);
+        }
+
+        if read_any {
+            Some(line)
+        } else {
+            None
+        }
+    }
+}
+
+impl<I: Iterator<Item = u8>> Iterator for CommandReader<I> {
+    type Item = Result<Command, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(line) = self.next_line() {
+            let line = preprocess_bytes(&line);
+            if line.iter().all(|b| *b == b' ') {
+                continue;
+            }
+            let line = String::from_utf8_lossy(&line);
+            return Some(line.parse());
+        }
+        None
+    }
+}
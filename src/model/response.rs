@@ -1,3 +1,5 @@
+use crate::model::ParseError;
+use crate::model::ParseErrorKind;
 use crate::model::types::*;
 use crate::model::CommandName;
 use alloc::string::String;
@@ -15,11 +17,13 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone)]
 pub enum ResponseData {
     Integer(u32),
+    Float(f32),
     String(String), // used only for name, version and showboard commands.
     Bool(Boolean),
     CommandNames(Vec<CommandName>),
     ListVertex(List<Vertex>),
     Move(Move),
+    Vertex(Vertex),
     Score(Score),
     VertexLists(Vec<List<Vertex>>),
 }
@@ -28,13 +32,15 @@ impl Display for ResponseData {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::Integer(i) => write!(f, "{}", i),
+            Self::Float(float) => write!(f, "{}", float),
             Self::String(s) => write!(f, "{}", s),
             Self::Bool(b) => write!(f, "{}", b),
-            Self::CommandNames(s) => s.into_iter().try_for_each(|s| writeln!(f, "{}", s)),
+            Self::CommandNames(s) => s.iter().try_for_each(|s| writeln!(f, "{}", s)),
             Self::ListVertex(v) => write!(f, "{}", v),
             Self::Move(m) => write!(f, "{}", m),
+            Self::Vertex(v) => write!(f, "{}", v),
             Self::Score(s) => write!(f, "{}", s),
-            Self::VertexLists(l) => l.into_iter().try_for_each(|v| writeln!(f, "{}", v)),
+            Self::VertexLists(l) => l.iter().try_for_each(|v| writeln!(f, "{}", v)),
         }
     }
 }
@@ -59,26 +65,120 @@ impl FromStr for ResponseData {
         if matches.len() > 1 {
             matches.pop();
             matches.pop();
-            if let Ok(_) = matches[0].parse::<CommandName>() {
-                return Ok(Self::CommandNames(matches.into_iter().map(|x| x.parse().unwrap()).collect()));
+            if matches.is_empty() {
+                return Err(ParseError::new(ParseErrorKind::WrongResponseData).with_input(str));
+            }
+            if matches[0].parse::<CommandName>().is_ok() {
+                return Ok(Self::CommandNames(matches.into_iter().map(|x| CommandName::from(x.to_string())).collect()));
             }
 
             let lines: Vec<Result<List<Vertex>, Self::Err>> = matches.into_iter().map(|x| x.parse::<List<Vertex>>()).collect();
             let mut multilines = Vec::new();
             for line in lines {
-                if let Err(_) = line {
+                if line.is_err() {
                     return Ok(Self::String(str.to_string()));
                 }
                 multilines.push(line?);
             }
             Ok(Self::VertexLists(multilines))
         } else {
-            Err(Self::Err::WrongResponseData)
+            Err(ParseErrorKind::WrongResponseData.into())
         }
     }
 }
 
+fn parse_lines<T, F>(str: &str, parse: F) -> Result<Vec<T>, crate::model::ParseError>
+where F: Fn(&str) -> Result<T, crate::model::ParseError> {
+    str.lines().filter(|l| !l.trim().is_empty()).map(|l| parse(l.trim())).collect()
+}
+
+fn parse_vertices(str: &str) -> Result<List<Vertex>, crate::model::ParseError> {
+    str.split_ascii_whitespace().map(|v| v.parse()).collect()
+}
+
 impl ResponseData {
+    /// Parses the data of a response to `command`, using the response type
+    /// the protocol defines for it rather than guessing.
+    /// Returns `None` when the response carries no data.
+    pub fn parse_for(command: CommandName, str: &str) -> Result<Option<Self>, crate::model::ParseError> {
+        let str = str.trim_matches(|c: char| c == '\n' || c == '\r' || c == ' ');
+        if str.is_empty() {
+            return Ok(None);
+        }
+
+        let data = match command {
+            CommandName::ProtocolVersion => Self::Integer(str.parse()?),
+            CommandName::Name |
+            CommandName::Version |
+            CommandName::Showboard |
+            CommandName::KataGetRules |
+            CommandName::KataGetParam |
+            CommandName::KataRawNn |
+            CommandName::KataListTimeSettings => Self::String(str.to_string()),
+            CommandName::KnownCommand => Self::Bool(str.parse()?),
+            CommandName::ListCommands => Self::CommandNames(
+                parse_lines(str, |l| Ok(CommandName::from(l.to_string())))?
+            ),
+            CommandName::FixedHandicap |
+            CommandName::PlaceFreeHandicap => Self::ListVertex(parse_vertices(str)?),
+            CommandName::Genmove |
+            CommandName::RegGenmove => Self::Vertex(str.parse()?),
+            CommandName::LzGenmoveAnalyze |
+            CommandName::KataGenmoveAnalyze |
+            CommandName::GenmoveAnalyze => {
+                match str.lines().find_map(|l| l.trim().strip_prefix("play ")) {
+                    Some(v) => Self::Vertex(v.trim().parse()?),
+                    None => return Ok(None),
+                }
+            },
+            CommandName::FinalScore => Self::Score(str.parse()?),
+            CommandName::FinalStatusList => Self::VertexLists(parse_lines(str, parse_vertices)?),
+            CommandName::GetKomi |
+            CommandName::Cputime |
+            CommandName::GomillCputime => Self::Float(str.parse()?),
+            CommandName::Unknown => str.parse()?,
+            _ => Self::String(str.to_string()),
+        };
+
+        Ok(Some(data))
+    }
+
+    pub fn to_float(self) -> Result<f32, Self> {
+        match self {
+            Self::Float(f) => Ok(f),
+            _ => Err(self),
+        }
+    }
+
+    pub const fn as_float(&self) -> Option<&f32> {
+        match self {
+            Self::Float(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    pub const fn is_float(&self) -> bool {
+        matches!(self, Self::Float(_))
+    }
+
+    pub fn to_vertex(self) -> Result<Vertex, Self> {
+        match self {
+            Self::Vertex(v) => Ok(v),
+            _ => Err(self),
+        }
+    }
+
+    pub const fn as_vertex(&self) -> Option<&Vertex> {
+        match self {
+            Self::Vertex(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub const fn is_vertex(&self) -> bool {
+        matches!(self, Self::Vertex(_))
+    }
+
     pub fn to_int(self) -> Result<u32, Self> {
         match self {
             Self::Integer(i) => Ok(i),
@@ -94,10 +194,7 @@ impl ResponseData {
     }
 
     pub const fn is_int(&self) -> bool {
-        match self {
-            Self::Integer(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::Integer(_))
     }
 
     pub fn to_name(self) -> Result<String, Self> {
@@ -115,10 +212,7 @@ impl ResponseData {
     }
 
     pub const fn is_name(&self) -> bool {
-        match self {
-            Self::String(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::String(_))
     }
 
     pub fn to_bool(self) -> Result<Boolean, Self> {
@@ -136,10 +230,7 @@ impl ResponseData {
     }
 
     pub const fn is_bool(&self) -> bool {
-        match self {
-            Self::Bool(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::Bool(_))
     }
 
     pub fn to_command_names(self) -> Result<Vec<CommandName>, Self> {
@@ -157,10 +248,7 @@ impl ResponseData {
     }
 
     pub const fn is_command_names(&self) -> bool {
-        match self {
-            Self::CommandNames(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::CommandNames(_))
     }
 
     pub fn to_list_vertex(self) -> Result<List<Vertex>, Self> {
@@ -178,10 +266,7 @@ impl ResponseData {
     }
 
     pub const fn is_list_vertex(&self) -> bool {
-        match self {
-            Self::ListVertex(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::ListVertex(_))
     }
 
     pub fn to_move(self) -> Result<Move, Self> {
@@ -199,10 +284,7 @@ impl ResponseData {
     }
 
     pub const fn is_move(&self) -> bool {
-        match self {
-            Self::Move(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::Move(_))
     }
 
     pub fn to_score(self) -> Result<Score, Self> {
@@ -220,10 +302,7 @@ impl ResponseData {
     }
 
     pub const fn is_score(&self) -> bool {
-        match self {
-            Self::Score(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::Score(_))
     }
 
     pub fn to_vertex_lists(self) -> Result<Vec<List<Vertex>>, Self> {
@@ -241,10 +320,7 @@ impl ResponseData {
     }
 
     pub const fn is_vertex_lists(&self) -> bool {
-        match self {
-            Self::VertexLists(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::VertexLists(_))
     }
 }
 
@@ -259,15 +335,13 @@ pub struct Response {
 impl Display for Response {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "=")?;
-        if self.id.is_some() {
-           write!(f, "{}", self.id.unwrap())?; 
-        }
-        if self.data.is_some() {
-            write!(f, " {}", self.data.clone().unwrap())?;
-            if let Some(ResponseData::CommandNames(_)) = self.data {
-                write!(f, "\n")
-            } else if let Some(ResponseData::VertexLists(_)) = self.data {
-                write!(f, "\n")
+        if let Some(id) = self.id {
+            write!(f, "{}", id)?;
+        }
+        if let Some(data) = &self.data {
+            write!(f, " {}", data)?;
+            if let ResponseData::CommandNames(_) | ResponseData::VertexLists(_) = data {
+                writeln!(f)
             } else {
                 write!(f, "\n\n")
             }
@@ -283,7 +357,7 @@ impl FromStr for Response {
 
     fn from_str(str: &str) -> Result<Self, Self::Err> {
         if str.get(0..1) != Some("=") {
-            return Err(Self::Err::WrongResponseFormat);
+            return Err(ParseErrorKind::WrongResponseFormat.into());
         }
         let str = str.split_at(1);
         let str = str.1;
@@ -296,7 +370,7 @@ impl FromStr for Response {
 
         let mut split = str.splitn(2, " ");    
         let mut id = None;
-        if let Ok(has_id) = split.next().unwrap().trim_end().parse::<u32>() {
+        if let Ok(has_id) = split.next().unwrap_or("").trim_end().parse::<u32>() {
             id = Some(has_id); 
         }
 
@@ -313,7 +387,7 @@ impl FromStr for Response {
                     data,
                 })
             },
-            None => return Ok(Self {
+            None => Ok(Self {
                 id,
                 data: None,
             }),
@@ -323,6 +397,24 @@ impl FromStr for Response {
 }
 
 impl Response {
+    /// Parses a response to `command`, decoding its data with
+    /// [`ResponseData::parse_for`].
+    pub fn parse_for(command: CommandName, str: &str) -> Result<Self, crate::model::ParseError> {
+        let str = str.strip_prefix('=').ok_or(ParseErrorKind::WrongResponseFormat)?;
+        let id_len = str.find(|c: char| !c.is_ascii_digit()).unwrap_or(str.len());
+        let (id, data) = str.split_at(id_len);
+        let id = if id.is_empty() {
+            None
+        } else {
+            Some(id.parse()?)
+        };
+
+        Ok(Self {
+            id,
+            data: ResponseData::parse_for(command, data)?,
+        })
+    }
+
     pub const fn mov(mov: Move) -> Self { //TODO: find a better name as move is a rust keyword
         Self {
             id: None,
@@ -337,6 +429,34 @@ impl Response {
         }
     }
 
+    pub const fn vertex(vertex: Vertex) -> Self {
+        Self {
+            id: None,
+            data: Some(ResponseData::Vertex(vertex)),
+        }
+    }
+
+    pub const fn vertex_with_id(id: u32, vertex: Vertex) -> Self {
+        Self {
+            id: Some(id),
+            data: Some(ResponseData::Vertex(vertex)),
+        }
+    }
+
+    pub const fn float(float: f32) -> Self {
+        Self {
+            id: None,
+            data: Some(ResponseData::Float(float)),
+        }
+    }
+
+    pub const fn float_with_id(id: u32, float: f32) -> Self {
+        Self {
+            id: Some(id),
+            data: Some(ResponseData::Float(float)),
+        }
+    }
+
     pub const fn empty() -> Self {
         Self {
             id: None,
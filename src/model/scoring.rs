@@ -0,0 +1,231 @@
+use crate::model::Board;
+use crate::model::Color;
+use crate::model::Score;
+use crate::model::Vertex;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// How points are counted at the end of the game.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ScoringMethod {
+    /// Stones plus surrounded points (Chinese, AGA).
+    #[default]
+    Area,
+    /// Surrounded points plus prisoners (Japanese, Korean).
+    Territory,
+}
+
+/// Points given to White for each handicap stone.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HandicapCompensation {
+    /// Japanese rules.
+    #[default]
+    None,
+    /// AGA rules: one point per handicap stone after the first.
+    NMinusOne,
+    /// Chinese rules: one point per handicap stone.
+    N,
+}
+
+impl HandicapCompensation {
+    pub const fn bonus(&self, handicap: u32) -> u32 {
+        match self {
+            Self::None => 0,
+            Self::NMinusOne => handicap.saturating_sub(1),
+            Self::N => handicap,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoringRules {
+    pub method: ScoringMethod,
+    pub komi: f32,
+    pub handicap_compensation: HandicapCompensation,
+}
+
+impl ScoringRules {
+    /// Chinese rules.
+    pub const fn area(komi: f32) -> Self {
+        Self {
+            method: ScoringMethod::Area,
+            komi,
+            handicap_compensation: HandicapCompensation::N,
+        }
+    }
+
+    /// Japanese rules.
+    pub const fn territory(komi: f32) -> Self {
+        Self {
+            method: ScoringMethod::Territory,
+            komi,
+            handicap_compensation: HandicapCompensation::None,
+        }
+    }
+
+    pub const fn with_handicap_compensation(mut self, compensation: HandicapCompensation) -> Self {
+        self.handicap_compensation = compensation;
+        self
+    }
+
+    /// Counts the position on `board` once the `dead` stones are removed.
+    /// `captures` are the prisoners taken during the game by Black and White,
+    /// `handicap` the number of handicap stones Black received.
+    ///
+    /// Empty regions bordered by both colours are dame. Eyes in seki are
+    /// territory under area scoring and dame under territory scoring, a group
+    /// being in seki when it has a liberty in dame, so neutral points must be
+    /// filled before counting.
+    pub fn score(&self, board: &Board, dead: &[Vertex], captures: [u32; 2], handicap: u32) -> ScoreDetail {
+        let size = board.size();
+        let mut board = board.clone();
+        let mut dead_stones = [0; 2];
+        for vertex in dead {
+            if let Some(color) = board.get(*vertex) {
+                dead_stones[color as usize] += 1;
+                let _ = board.set(*vertex, None, Color::Black);
+            }
+        }
+
+        let mut regions = Vec::new();
+        let mut neutral = vec![false; size.area()];
+        let mut seen = vec![false; size.area()];
+        for vertex in size.vertices() {
+            let index = match vertex.to_index(size) {
+                Some(i) => i,
+                None => continue,
+            };
+            if seen[index] || board.get(vertex).is_some() {
+                continue;
+            }
+
+            let (region, borders) = empty_region(&board, vertex, &mut seen);
+            if borders == [true, true] {
+                for i in region.iter().filter_map(|v| v.to_index(size)) {
+                    neutral[i] = true;
+                }
+            }
+            regions.push((region, borders));
+        }
+
+        let mut territory = [0; 2];
+        let mut dame = 0;
+        for (region, borders) in &regions {
+            let owner = match borders {
+                [true, false] => Some(Color::Black),
+                [false, true] => Some(Color::White),
+                _ => None,
+            };
+            let owner = owner.filter(|_| self.method == ScoringMethod::Area || !in_seki(&board, region, &neutral));
+            match owner {
+                Some(color) => territory[color as usize] += region.len() as u32,
+                None => dame += region.len() as u32,
+            }
+        }
+
+        ScoreDetail {
+            method: self.method,
+            komi: self.komi,
+            handicap_bonus: self.handicap_compensation.bonus(handicap),
+            territory,
+            stones: [board.count(Color::Black) as u32, board.count(Color::White) as u32],
+            prisoners: [
+                captures[Color::Black as usize] + dead_stones[Color::White as usize],
+                captures[Color::White as usize] + dead_stones[Color::Black as usize],
+            ],
+            dame,
+        }
+    }
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        Self::area(7.5)
+    }
+}
+
+/// The empty region containing `start`, and whether it touches black and
+/// white stones.
+fn empty_region(board: &Board, start: Vertex, seen: &mut [bool]) -> (Vec<Vertex>, [bool; 2]) {
+    let size = board.size();
+    let mut borders = [false; 2];
+    let mut region = Vec::new();
+    let mut stack = vec![start];
+    if let Some(i) = start.to_index(size) {
+        seen[i] = true;
+    }
+    while let Some(vertex) = stack.pop() {
+        region.push(vertex);
+        for n in board.neighbours_of(vertex) {
+            match board.get(n) {
+                Some(color) => borders[color as usize] = true,
+                None => {
+                    if let Some(i) = n.to_index(size) {
+                        if !seen[i] {
+                            seen[i] = true;
+                            stack.push(n);
+                        }
+                    }
+                },
+            }
+        }
+    }
+    (region, borders)
+}
+
+/// Whether a group around `region` has a liberty on a `neutral` point.
+fn in_seki(board: &Board, region: &[Vertex], neutral: &[bool]) -> bool {
+    let size = board.size();
+    region.iter()
+        .flat_map(|v| board.neighbours_of(*v))
+        .filter(|n| board.get(*n).is_some())
+        .flat_map(|stone| board.group_at(stone))
+        .flat_map(|stone| board.neighbours_of(stone))
+        .any(|n| board.get(n).is_none() && n.to_index(size).is_some_and(|i| neutral[i]))
+}
+
+/// Breakdown of a counted position, indexed by colour.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreDetail {
+    pub method: ScoringMethod,
+    pub komi: f32,
+    /// Extra points for White, from the handicap stones.
+    pub handicap_bonus: u32,
+    pub territory: [u32; 2],
+    /// Stones left on the board once the dead ones are removed.
+    pub stones: [u32; 2],
+    /// Captures during the game plus dead stones removed at the end.
+    pub prisoners: [u32; 2],
+    pub dame: u32,
+}
+
+impl ScoreDetail {
+    /// Points of `color`, komi and handicap compensation included.
+    pub fn points(&self, color: Color) -> f32 {
+        let i = color as usize;
+        let points = match self.method {
+            ScoringMethod::Area => self.territory[i] + self.stones[i],
+            ScoringMethod::Territory => self.territory[i] + self.prisoners[i],
+        } as f32;
+        match color {
+            Color::Black => points,
+            Color::White => points + self.komi + self.handicap_bonus as f32,
+        }
+    }
+
+    /// Black's points minus White's points.
+    pub fn difference(&self) -> f32 {
+        self.points(Color::Black) - self.points(Color::White)
+    }
+
+    pub fn score(&self) -> Score {
+        Score::from_difference(self.difference())
+    }
+}
+
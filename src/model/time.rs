@@ -0,0 +1,265 @@
+use crate::model::Color;
+use crate::model::GtpCommand;
+use crate::model::ParseError;
+use crate::model::ParseErrorKind;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Display;
+use core::str::FromStr;
+use core::time::Duration;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// A time control, as set with `time_settings` or `kgs-time_settings`.
+/// Times are sent to engines in whole seconds.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TimeControl {
+    #[default]
+    None,
+    Absolute { main: Duration },
+    /// `stones` moves to play in every `period` once main time is over.
+    Canadian { main: Duration, period: Duration, stones: u32 },
+    /// `periods` periods of `period`, one used up by every move exceeding it.
+    Japanese { main: Duration, period: Duration, periods: u32 },
+    /// `increment` added after every move.
+    Fischer { main: Duration, increment: Duration },
+}
+
+impl TimeControl {
+    /// Whether `kgs-time_settings` can express it, KGS having no Fischer time.
+    pub const fn has_kgs_form(&self) -> bool {
+        !matches!(self, Self::Fischer { .. })
+    }
+
+    /// The closest `time_settings` command. Japanese byo-yomi is sent as
+    /// one stone per period, Fischer as absolute time kept up to date by
+    /// `time_left`.
+    pub fn to_time_settings(&self) -> GtpCommand {
+        let (main, byo_yomi, stones) = match *self {
+            Self::None => (Duration::ZERO, Duration::from_secs(1), 0),
+            Self::Absolute { main } |
+            Self::Fischer { main, .. } => (main, Duration::ZERO, 0),
+            Self::Canadian { main, period, stones } => (main, period, stones),
+            Self::Japanese { main, period, .. } => (main, period, 1),
+        };
+        GtpCommand::TimeSettings { main: secs(main), byo_yomi: secs(byo_yomi), stones }
+    }
+
+    /// `kgs-time_settings` when it can express the control, `time_settings` otherwise.
+    pub fn to_command(&self, kgs: bool) -> GtpCommand {
+        if kgs && self.has_kgs_form() {
+            GtpCommand::KgsTimeSettings(*self)
+        } else {
+            self.to_time_settings()
+        }
+    }
+}
+
+fn secs(time: Duration) -> u32 {
+    u32::try_from(time.as_secs()).unwrap_or(u32::MAX)
+}
+
+/// Writes the `kgs-time_settings` arguments, e.g. `byoyomi 600 30 5`, and
+/// `fischer <main> <increment>` as KataGo's `kata-time_settings` does.
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Self::None => write!(f, "none"),
+            Self::Absolute { main } => write!(f, "absolute {}", main.as_secs()),
+            Self::Canadian { main, period, stones } =>
+                write!(f, "canadian {} {} {}", main.as_secs(), period.as_secs(), stones),
+            Self::Japanese { main, period, periods } =>
+                write!(f, "byoyomi {} {} {}", main.as_secs(), period.as_secs(), periods),
+            Self::Fischer { main, increment } =>
+                write!(f, "fischer {} {}", main.as_secs(), increment.as_secs()),
+        }
+    }
+}
+
+impl FromStr for TimeControl {
+    type Err = ParseError;
+
+    fn from_str(str: &str) -> Result<Self, Self::Err> {
+        let tokens: Vec<&str> = str.split_ascii_whitespace().collect();
+        let number = |token: &str| token.parse::<u64>().map_err(|e| ParseError::from(e).with_input(token));
+        let time = |token: &str| number(token).map(Duration::from_secs);
+        let count = |token: &str| token.parse::<u32>().map_err(|e| ParseError::from(e).with_input(token));
+
+        Ok(match tokens.as_slice() {
+            ["none"] => Self::None,
+            ["absolute", main] => Self::Absolute { main: time(main)? },
+            ["canadian", main, period, stones] =>
+                Self::Canadian { main: time(main)?, period: time(period)?, stones: count(stones)? },
+            ["byoyomi", main, period, periods] =>
+                Self::Japanese { main: time(main)?, period: time(period)?, periods: count(periods)? },
+            ["fischer", main, increment] => Self::Fischer { main: time(main)?, increment: time(increment)? },
+            _ => return Err(ParseError::new(ParseErrorKind::WrongArgs)
+                .expected("none, absolute, canadian, byoyomi or fischer with their times")
+                .with_input(str)),
+        })
+    }
+}
+
+/// The time a player has left.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PlayerClock {
+    /// Main time left, or time left in the current period once in byo-yomi.
+    pub remaining: Duration,
+    /// Once in byo-yomi, Canadian stones left to play in the period or
+    /// Japanese periods left.
+    pub stones: u32,
+    pub in_byo_yomi: bool,
+    /// Lost on time.
+    pub flagged: bool,
+}
+
+impl PlayerClock {
+    fn new(control: TimeControl) -> Self {
+        let mut clock = Self::default();
+        match control {
+            TimeControl::None => {},
+            TimeControl::Absolute { main } |
+            TimeControl::Fischer { main, .. } => clock.remaining = main,
+            TimeControl::Canadian { main, period, stones: count } |
+            TimeControl::Japanese { main, period, periods: count } => {
+                clock.remaining = main;
+                if main.is_zero() {
+                    clock.enter_byo_yomi(period, count);
+                }
+            },
+        }
+        clock
+    }
+
+    fn enter_byo_yomi(&mut self, period: Duration, count: u32) -> bool {
+        if period.is_zero() || count == 0 {
+            return false;
+        }
+        self.in_byo_yomi = true;
+        self.remaining = period;
+        self.stones = count;
+        true
+    }
+
+    fn flag(&mut self) -> bool {
+        self.remaining = Duration::ZERO;
+        self.flagged = true;
+        false
+    }
+
+    /// Spends main time, returning the time left to spend in byo-yomi.
+    fn spend_main(&mut self, elapsed: Duration) -> Option<Duration> {
+        if self.in_byo_yomi {
+            return Some(elapsed);
+        }
+        match self.remaining.checked_sub(elapsed) {
+            Some(remaining) => {
+                self.remaining = remaining;
+                None
+            },
+            None => {
+                let overtime = elapsed - self.remaining;
+                self.remaining = Duration::ZERO;
+                Some(overtime)
+            },
+        }
+    }
+
+    fn record_move(&mut self, control: TimeControl, elapsed: Duration) -> bool {
+        if self.flagged {
+            return false;
+        }
+        match control {
+            TimeControl::None => true,
+            TimeControl::Absolute { .. } => self.spend_main(elapsed).is_none() || self.flag(),
+            TimeControl::Fischer { increment, .. } => {
+                if self.spend_main(elapsed).is_some() {
+                    return self.flag();
+                }
+                self.remaining += increment;
+                true
+            },
+            TimeControl::Canadian { period, stones, .. } => {
+                let Some(overtime) = self.spend_main(elapsed) else {
+                    return true;
+                };
+                if !self.in_byo_yomi && !self.enter_byo_yomi(period, stones) {
+                    return self.flag();
+                }
+                match self.remaining.checked_sub(overtime) {
+                    Some(remaining) => self.remaining = remaining,
+                    None => return self.flag(),
+                }
+                self.stones -= 1;
+                if self.stones == 0 {
+                    self.enter_byo_yomi(period, stones);
+                }
+                true
+            },
+            TimeControl::Japanese { period, periods, .. } => {
+                let Some(mut overtime) = self.spend_main(elapsed) else {
+                    return true;
+                };
+                if !self.in_byo_yomi && !self.enter_byo_yomi(period, periods) {
+                    return self.flag();
+                }
+                while overtime > self.remaining {
+                    overtime -= self.remaining;
+                    self.stones -= 1;
+                    if self.stones == 0 {
+                        return self.flag();
+                    }
+                    self.remaining = period;
+                }
+                self.remaining = period;
+                true
+            },
+        }
+    }
+}
+
+/// Remaining time of both players under a time control.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GameClock {
+    control: TimeControl,
+    clocks: [PlayerClock; 2],
+}
+
+impl GameClock {
+    pub fn new(control: TimeControl) -> Self {
+        let clock = PlayerClock::new(control);
+        Self { control, clocks: [clock, clock] }
+    }
+
+    pub const fn control(&self) -> TimeControl {
+        self.control
+    }
+
+    pub const fn get(&self, color: Color) -> &PlayerClock {
+        &self.clocks[color as usize]
+    }
+
+    /// Counts a move of `color` which took `elapsed`. Returns `false` when
+    /// `color` ran out of time, now or before.
+    pub fn record_move(&mut self, color: Color, elapsed: Duration) -> bool {
+        self.clocks[color as usize].record_move(self.control, elapsed)
+    }
+
+    /// The `time_left` command for `color`, `None` without time control.
+    /// For Japanese byo-yomi `stones` is the number of periods left, as KGS
+    /// sends it.
+    pub fn time_left(&self, color: Color) -> Option<GtpCommand> {
+        if self.control == TimeControl::None {
+            return None;
+        }
+        let clock = self.get(color);
+        Some(GtpCommand::TimeLeft {
+            color,
+            time: secs(clock.remaining),
+            stones: if clock.in_byo_yomi { clock.stones } else { 0 },
+        })
+    }
+}
@@ -1,5 +1,5 @@
+use crate::model::ParseErrorKind;
 use crate::model::Entity;
-use alloc::borrow::ToOwned;
 use alloc::fmt::Display;
 use core::fmt;
 use alloc::format;
@@ -11,6 +11,9 @@ use core::ops::IndexMut;
 use core::ops::Index;
 use core::str::FromStr;
 use super::ParseError;
+use super::RectSize;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
 //use log::debug;
 
@@ -19,31 +22,40 @@ use super::ParseError;
 #[macro_export]
 macro_rules! collection {
     ($($elem:expr),*) => {
-        {
-            let mut v : Vec<crate::model::SimpleEntity> = Vec::new();
-
-            $(
-                v.push($elem);
-            )*
-            crate::model::Collection::from_vec(v)
-        }
+        <$crate::model::Collection as core::iter::FromIterator<$crate::model::SimpleEntity>>::from_iter([$($elem),*])
     };
 }
 
 #[macro_export]
 macro_rules! list {
     ($t:ty; $($elem:expr),*) => {
-        {
-            let mut v : Vec<$t> = Vec::new();
+        <$crate::model::List<$t> as core::iter::FromIterator<$t>>::from_iter([$($elem),*])
+    };
+}
 
-            $(
-                v.push($elem);
-            )*
-            crate::model::types::List::from_vec(v)
-        }
+/// Serializes types as their GTP text, e.g. `"D4"` or `"B+7.5"`.
+#[cfg(feature = "serde")]
+macro_rules! serde_as_text {
+    ($($t:ty),*) => {
+        $(
+            impl Serialize for $t {
+                fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $t {
+                fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+                }
+            }
+        )*
     };
 }
 
+#[cfg(feature = "serde")]
+serde_as_text!(Boolean, Score, Vertex, Color, Move, SimpleEntity);
+
 #[derive(Debug, Clone, Copy)]
 pub enum Boolean {
     True,
@@ -66,46 +78,186 @@ impl FromStr for Boolean {
         match str.to_uppercase().as_str() {
             "TRUE" => Ok(Self::True),
             "FALSE" => Ok(Self::False),
-            _ => Err(Self::Err::WrongBool),
+            _ => Err(ParseError::new(ParseErrorKind::WrongBool).expected("true or false").with_input(str)),
         }
     }
 }
 
-#[derive(Debug, Clone)]
-pub struct Score(String);
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WinReason {
+    Score,
+    Resign,
+    Time,
+    Forfeit,
+}
+
+impl WinReason {
+    const fn short(&self) -> &'static str {
+        match self {
+            Self::Score => "",
+            Self::Resign => "R",
+            Self::Time => "T",
+            Self::Forfeit => "F",
+        }
+    }
+
+    const fn long(&self) -> &'static str {
+        match self {
+            Self::Score => "",
+            Self::Resign => "Resign",
+            Self::Time => "Time",
+            Self::Forfeit => "Forfeit",
+        }
+    }
+}
+
+/// Result of a game, as returned by `final_score` or stored in SGF `RE`.
+///
+/// Displays in the short form (`0`, `B+7.5`, `W+R`, `B+T`, `W+F`, `Void`);
+/// the alternate form `{:#}` spells out `Draw`, `W+Resign`, `B+Time` and `W+Forfeit`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Score {
+    Draw,
+    Win {
+        color: Color,
+        /// Points ahead, unknown for `B+` and for non-counted wins.
+        margin: Option<f32>,
+        reason: WinReason,
+    },
+    /// No result, the game does not count.
+    Void,
+}
 
 impl FromStr for Score {
     type Err = crate::model::ParseError;
 
     fn from_str(str: &str) -> Result<Self, Self::Err> {
-        if str.is_empty() {
-            return Err(Self::Err::EmptyString);
-        } else if str == "0" {
-            Ok(Self(str.to_string()))
+        let wrong_score = || ParseError::new(ParseErrorKind::WrongScore)
+            .expected("a score like W+7.5, B+R, 0 or Void")
+            .with_input(str);
+
+        match str {
+            "" => return Err(ParseErrorKind::EmptyString.into()),
+            "0" | "Draw" => return Ok(Self::Draw),
+            "Void" => return Ok(Self::Void),
+            _ => {},
+        }
+
+        let (color, result) = str.split_once('+').ok_or_else(wrong_score)?;
+        let color = match color {
+            "W" | "B" => color.parse()?,
+            _ => return Err(ParseError::new(ParseErrorKind::WrongColor).expected("B or W").at_token(str, color)),
+        };
+
+        let (margin, reason) = match result {
+            "" => (None, WinReason::Score),
+            "R" | "Resign" => (None, WinReason::Resign),
+            "T" | "Time" => (None, WinReason::Time),
+            "F" | "Forfeit" => (None, WinReason::Forfeit),
+            _ => {
+                let margin = result.parse::<f32>().map_err(|e| ParseError::from(e).at_token(str, result))?;
+                if !margin.is_finite() || margin < 0.0 {
+                    return Err(wrong_score());
+                }
+                (Some(margin), WinReason::Score)
+            },
+        };
+
+        Ok(Self::Win { color, margin, reason })
+    }
+}
+
+impl Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Draw if f.alternate() => write!(f, "Draw"),
+            Self::Draw => write!(f, "0"),
+            Self::Void => write!(f, "Void"),
+            Self::Win { color, margin, reason } => {
+                write!(f, "{}+", color)?;
+                match (reason, margin) {
+                    (WinReason::Score, Some(margin)) => write!(f, "{}", margin),
+                    _ if f.alternate() => write!(f, "{}", reason.long()),
+                    _ => write!(f, "{}", reason.short()),
+                }
+            },
+        }
+    }
+}
+
+impl Score {
+    /// Score of a counted game given Black's points minus White's points.
+    pub fn from_difference(difference: f32) -> Self {
+        if difference == 0.0 {
+            Self::Draw
         } else {
-            let split: Vec<&str> = str.split('+').collect();
-            if split.len() > 2 {
-                return Err(Self::Err::WrongScore);
+            Self::Win {
+                color: if difference > 0.0 { Color::Black } else { Color::White },
+                margin: Some(difference.abs()),
+                reason: WinReason::Score,
             }
+        }
+    }
 
-            match split[0] {
-                "W" | "B" => (),
-                _ => {return Err(Self::Err::WrongColor);}
-            }
+    pub const fn winner(&self) -> Option<Color> {
+        match self {
+            Self::Win { color, .. } => Some(*color),
+            _ => None,
+        }
+    }
 
-            split[1].parse::<f32>()?;
+    pub const fn reason(&self) -> Option<WinReason> {
+        match self {
+            Self::Win { reason, .. } => Some(*reason),
+            _ => None,
+        }
+    }
 
-            Ok(Self(str.to_string()))
+    /// Points between the players, `0` for a draw.
+    pub const fn margin(&self) -> Option<f32> {
+        match self {
+            Self::Win { margin, .. } => *margin,
+            Self::Draw => Some(0.0),
+            Self::Void => None,
         }
     }
-}
 
-impl Display for Score {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.0)
+    /// Margin from `color`'s point of view: negative when it lost.
+    pub fn margin_for(&self, color: Color) -> Option<f32> {
+        let margin = self.margin()?;
+        match self.winner() {
+            Some(winner) if winner != color => Some(-margin),
+            _ => Some(margin),
+        }
+    }
+
+    /// Game result for `color` as used by rating systems:
+    /// `1` for a win, `0.5` for a draw, `0` for a loss and nothing for a void game.
+    pub fn outcome_for(&self, color: Color) -> Option<f32> {
+        match self {
+            Self::Win { color: winner, .. } if *winner == color => Some(1.0),
+            Self::Win { .. } => Some(0.0),
+            Self::Draw => Some(0.5),
+            Self::Void => None,
+        }
+    }
+
+    pub const fn is_draw(&self) -> bool {
+        matches!(self, Self::Draw)
+    }
+
+    pub const fn is_void(&self) -> bool {
+        matches!(self, Self::Void)
     }
 }
 
+/// Largest board side supported by the protocol.
+pub const MAX_BOARD_SIZE: u32 = 25;
+
+// We skip I on the goban for readability
+const COLUMNS: &[u8; MAX_BOARD_SIZE as usize] = b"ABCDEFGHJKLMNOPQRSTUVWXYZ";
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Vertex {
     Coord(u8, u8),
@@ -118,32 +270,28 @@ impl Entity for Vertex {}
 impl FromStr for Vertex {
     type Err = ParseError;
 
-    fn from_str(str: &str) -> Result<Self, Self::Err> {
-        if str.is_empty() {
-            return Err(Self::Err::EmptyString);
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        if input.is_empty() {
+            return Err(ParseErrorKind::EmptyString.into());
         }
-        let str = str.to_uppercase();
+        let wrong_coordinates = || ParseError::new(ParseErrorKind::WrongCoordinates)
+            .expected("a vertex like D4, pass or resign")
+            .with_input(input);
+        let str = input.to_uppercase();
         if str == "PASS" {
             return Ok(Self::Pass)
         } else if str == "RESIGN" {
             return Ok(Self::Resign)
-        } else if str == "" {
-            return Err(ParseError::WrongCoordinates)
+        } else if str.is_empty() {
+            return Err(wrong_coordinates())
         }
 
-        let mut c = str.bytes().next().unwrap() - 64;
-        if (1..=20).contains(&c) {
-            if c > 9 { // We skip I on the goban for readability
-                c -= 1;
-            }
-
-            if let Ok(number) = str[1..].parse::<u8>() {
-                Ok(Self::Coord(c, number))
-            } else {
-                Err(ParseError::WrongCoordinates)
-            }
-        } else {
-            Err(ParseError::WrongCoordinates)
+        let column = COLUMNS.iter().position(|c| *c == str.as_bytes()[0]);
+        let row = str.get(1..).filter(|row| row.bytes().all(|b| b.is_ascii_digit()));
+        match (column, row.map(|row| row.parse::<u8>())) {
+            (Some(column), Some(Ok(row))) if (1..=MAX_BOARD_SIZE as u8).contains(&row) =>
+                Ok(Self::Coord(column as u8 + 1, row)),
+            _ => Err(wrong_coordinates()),
         }
     }
 }
@@ -152,11 +300,11 @@ impl Display for Vertex {
     fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
         match self {
             Self::Coord(x, y) => {
-                let mut x : u8 = x.clone();
-                if x > 8 { // We skip I on the goban for readability
-                    x = x + 1;
+                let column = (*x as usize).checked_sub(1).and_then(|x| COLUMNS.get(x));
+                match column {
+                    Some(c) => write!(f, "{}{}", *c as char, y),
+                    None => write!(f, "?{}", y),
                 }
-                write!(f, "{}{}", ((x+64) as char).to_uppercase(), y)
             },
             Self::Pass => write!(f, "PASS"),
             Self::Resign => write!(f, "RESIGN"),
@@ -190,6 +338,71 @@ impl Vertex {
             Self::Resign => None,
         }
     }
+
+    /// Parses a vertex and checks it lies on a board of the given size.
+    pub fn parse_for(str: &str, size: RectSize) -> Result<Self, ParseError> {
+        str.parse::<Self>()?.validate(size)
+    }
+
+    /// Checks a coordinate lies on a board of the given size.
+    /// `Pass` and `Resign` are always valid.
+    pub fn validate(self, size: RectSize) -> Result<Self, ParseError> {
+        if self.is_on_board(size) || !self.is_coord() {
+            Ok(self)
+        } else {
+            Err(ParseError::new(ParseErrorKind::WrongCoordinates)
+                .expected("a vertex on the board")
+                .with_input(&self.to_string()))
+        }
+    }
+
+    pub const fn is_coord(&self) -> bool {
+        matches!(self, Self::Coord(_, _))
+    }
+
+    pub const fn is_on_board(&self, size: RectSize) -> bool {
+        match self {
+            Self::Coord(x, y) => *x >= 1 && *y >= 1 && *x as u32 <= size.width() && *y as u32 <= size.height(),
+            _ => false,
+        }
+    }
+
+    /// 0-based (row, column), with row 0 being the top row of the board as
+    /// displayed, i.e. the row with the highest number.
+    pub const fn to_row_col(&self, size: RectSize) -> Option<(usize, usize)> {
+        match self {
+            Self::Coord(x, y) if self.is_on_board(size) =>
+                Some(((size.height() - *y as u32) as usize, (*x - 1) as usize)),
+            _ => None,
+        }
+    }
+
+    /// Fails outside of `size` and beyond [`MAX_BOARD_SIZE`].
+    pub const fn from_row_col(row: usize, col: usize, size: RectSize) -> Option<Self> {
+        if row >= size.height() as usize || col >= size.width() as usize || col >= MAX_BOARD_SIZE as usize {
+            return None;
+        }
+        if row >= MAX_BOARD_SIZE as usize || size.height() as usize - row > MAX_BOARD_SIZE as usize {
+            return None;
+        }
+        Some(Self::Coord(col as u8 + 1, (size.height() as usize - row) as u8))
+    }
+
+    /// Row-major index starting from the top left corner, as used by KataGo
+    /// for ownership and policy arrays.
+    pub const fn to_index(&self, size: RectSize) -> Option<usize> {
+        match self.to_row_col(size) {
+            Some((row, col)) => Some(row * size.width() as usize + col),
+            None => None,
+        }
+    }
+
+    pub const fn from_index(index: usize, size: RectSize) -> Option<Self> {
+        if size.width() == 0 {
+            return None;
+        }
+        Self::from_row_col(index / size.width() as usize, index % size.width() as usize, size)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -214,13 +427,13 @@ impl FromStr for Color {
 
     fn from_str(str: &str) -> Result<Self, Self::Err> {
         if str.is_empty() {
-            return Err(Self::Err::EmptyString);
+            return Err(ParseErrorKind::EmptyString.into());
         }
         let str = str.to_uppercase();
         match &*str {
             "B" | "BLACK" => Ok(Self::Black),
             "W" | "WHITE" => Ok(Self::White),
-            _ => Err(ParseError::WrongColor),
+            _ => Err(ParseError::new(ParseErrorKind::WrongColor).expected("B, W, black or white").with_input(&str)),
         }
     }
 }
@@ -243,6 +456,15 @@ impl core::cmp::PartialEq<String> for Color {
     }
 }
 
+impl Color {
+    pub const fn opponent(&self) -> Self {
+        match self {
+            Self::Black => Self::White,
+            Self::White => Self::Black,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Move {
     pub color: Color,
@@ -262,14 +484,19 @@ impl FromStr for Move {
 
     fn from_str(str: &str) -> Result<Self, Self::Err> {
         if str.is_empty() {
-            return Err(ParseError::EmptyString);
+            return Err(ParseErrorKind::EmptyString.into());
+        }
+        let wrong_move = || ParseError::new(ParseErrorKind::WrongArgs)
+            .expected("a color followed by a vertex")
+            .with_input(str);
+        let mut tokens = str.split_ascii_whitespace();
+        match (tokens.next(), tokens.next(), tokens.next()) {
+            (Some(color), Some(vertex), None) => Ok(Move {
+                color: color.parse()?,
+                vertex: vertex.parse()?,
+            }),
+            _ => Err(wrong_move()),
         }
-        let str = str.to_uppercase();
-        let mut str = str.split_ascii_whitespace();
-        Ok(Move {
-            color: str.next().unwrap().parse()?,
-            vertex: str.next().unwrap().parse()?,
-        })
     }
 }
 
@@ -319,7 +546,7 @@ impl FromStr for SimpleEntity {
         } else if let Ok(m) = str.parse::<Move>() {
             Ok(SimpleEntity::Move(m))
         } else {
-            Err(ParseError::WrongSimpleEntity)
+            Err(ParseError::new(ParseErrorKind::WrongSimpleEntity).with_input(str))
         }
     }
 }
@@ -365,7 +592,8 @@ impl SimpleEntity {
     }
 }
 
-#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Default, Hash)]
 pub struct Collection(Vec<SimpleEntity>);
 
 impl Entity for Collection {}
@@ -390,12 +618,9 @@ impl FromStr for Collection {
     type Err = ParseError;
 
     fn from_str(str: &str) -> Result<Self, Self::Err> {
-        let elems : Vec<SimpleEntity> = str.to_uppercase().split_ascii_whitespace()
-            .map(|e| if let Ok(elem) = e.parse::<SimpleEntity>() {
-                elem
-            } else {
-                panic!()
-            }).collect();
+        let elems = str.split_ascii_whitespace()
+            .map(|e| e.parse::<SimpleEntity>().map_err(|err| err.at_token(str, e)))
+            .collect::<Result<Vec<SimpleEntity>, ParseError>>()?;
 
         Ok(Self(elems))
     }
@@ -410,19 +635,11 @@ impl Index<usize> for Collection {
 }
 
 impl IndexMut<usize> for Collection {
-    fn index_mut<'a>(&'a mut self, i: usize) -> &'a mut Self::Output {
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
         &mut self.0[i]
     }
 }
 
-impl Default for Collection {
-    fn default() -> Self {
-        Self {
-            0: Vec::new(),
-        }
-    }
-}
-
 impl From<Vec<SimpleEntity>> for Collection {
     fn from(vec: Vec<SimpleEntity>) -> Self {
         Self(vec)
@@ -444,21 +661,21 @@ impl IntoIterator for Collection {
     }
 }
 
-impl<'a> IntoIterator for &'a Collection {
+impl IntoIterator for &Collection {
     type Item = SimpleEntity;
     type IntoIter = alloc::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.to_owned().into_iter()
+        self.0.clone().into_iter()
     }
 }
 
-impl<'a> IntoIterator for &'a mut Collection {
+impl IntoIterator for &mut Collection {
     type Item = SimpleEntity;
     type IntoIter = alloc::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.to_owned().into_iter()
+        self.0.clone().into_iter()
     }
 }
 
@@ -500,7 +717,8 @@ impl Collection {
     }
 }
 
-#[derive(Debug, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Hash)]
 pub struct List<T : Entity>(Vec<T>);
 
 impl<T: Entity> Entity for List<T> {}
@@ -527,16 +745,12 @@ impl<T: Entity> FromStr for List<T> {
     fn from_str(str: &str) -> Result<Self, Self::Err> {
         let check_line_return: Vec<&str> = str.split('\n').collect();
         if check_line_return.len() > 2 {
-            return Err(Self::Err::WrongArgs);
+            return Err(ParseErrorKind::WrongArgs.into());
         }
 
-        let elems : Vec<T> = str.split_ascii_whitespace()
-            .take_while(|e| e.clone() != "".to_string())
-            .map(|e| if let Ok(elem) = e.parse::<T>() {
-                elem
-            } else {
-                panic!()
-            }).collect();
+        let elems = str.split_ascii_whitespace()
+            .map(|e| e.parse::<T>().map_err(|_| ParseError::new(ParseErrorKind::WrongArgs).at_token(str, e)))
+            .collect::<Result<Vec<T>, ParseError>>()?;
 
         Ok(Self(elems))
     }
@@ -551,16 +765,14 @@ impl<T: Entity> Index<usize> for List<T> {
 }
 
 impl<T: Entity> IndexMut<usize> for List<T> {
-    fn index_mut<'a>(&'a mut self, i: usize) -> &'a mut Self::Output {
+    fn index_mut(&mut self, i: usize) -> &mut Self::Output {
         &mut self.0[i]
     }
 }
 
 impl<T: Entity> Default for List<T> {
     fn default() -> Self {
-        Self {
-            0: Vec::new(),
-        }
+        Self(Vec::new())
     }
 }
 
@@ -585,21 +797,21 @@ impl<T: Entity> IntoIterator for List<T> {
     }
 }
 
-impl<'a, T: Entity> IntoIterator for &'a List<T> {
+impl<T: Entity> IntoIterator for &List<T> {
     type Item = T;
     type IntoIter = alloc::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.to_owned().into_iter()
+        self.0.clone().into_iter()
     }
 }
 
-impl<'a, T: Entity> IntoIterator for &'a mut List<T> {
+impl<T: Entity> IntoIterator for &mut List<T> {
     type Item = T;
     type IntoIter = alloc::vec::IntoIter<Self::Item>;
 
     fn into_iter(self) -> Self::IntoIter {
-        self.0.to_owned().into_iter()
+        self.0.clone().into_iter()
     }
 }
 
@@ -0,0 +1,317 @@
+//! Reviewing a whole game with `kata-analyze`.
+//!
+//! Every position of the main line is analysed in turn, the move played is
+//! compared with the engine's best move and judged by how many points and
+//! how much winrate it lost. The review can be written back to the record
+//! as comments, or saved as JSON.
+//!
+//! ```no_run
+//! use libgtp::review::{review, ReviewSettings};
+//! use libgtp::sgf::parse_collection;
+//! use libgtp::Controller;
+//!
+//! let mut trees = parse_collection(&std::fs::read_to_string("game.sgf").unwrap()).unwrap();
+//! let mut controller = Controller::new("katago", &["gtp", "-config", "gtp.cfg"]);
+//! let game_review = review(&mut controller, &trees[0], &ReviewSettings::visits(400)).unwrap();
+//! game_review.annotate(&mut trees[0]).unwrap();
+//! println!("{}", trees[0]);
+//! ```
+
+use crate::model::{AnalyzeArgs, Color, GtpCommand, Info, Move, ParseError, Perspective, Vertex};
+use crate::sgf::GameTree;
+use crate::Controller;
+use serde::Serialize;
+use std::fmt;
+use std::fmt::Display;
+use std::io::{Error, ErrorKind};
+use std::time::{Duration, Instant};
+
+/// Pause between two checks of the latest info line.
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How long each position is analysed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Budget {
+    /// Until the candidate moves total this many visits.
+    Visits(u64),
+    Time(Duration),
+}
+
+/// A move losing at least `points` or at least `winrate` reaches the threshold.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Threshold {
+    pub points: f32,
+    pub winrate: f32,
+}
+
+impl Threshold {
+    pub const fn new(points: f32, winrate: f32) -> Self {
+        Self { points, winrate }
+    }
+
+    pub fn is_reached(&self, point_loss: f32, winrate_loss: f32) -> bool {
+        point_loss >= self.points || winrate_loss >= self.winrate
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReviewSettings {
+    pub budget: Budget,
+    /// Centiseconds between info lines, the `kata-analyze` interval.
+    pub interval: u32,
+    /// Longest time spent on a position, in case the budget is never reached.
+    pub max_time: Duration,
+    /// Point of view of the engine's values, `reportAnalysisWinratesAs`.
+    pub perspective: Perspective,
+    pub inaccuracy: Threshold,
+    pub mistake: Threshold,
+    pub blunder: Threshold,
+}
+
+impl ReviewSettings {
+    pub fn visits(visits: u64) -> Self {
+        Self {
+            budget: Budget::Visits(visits),
+            interval: 10,
+            max_time: Duration::from_secs(60),
+            perspective: Perspective::SideToMove,
+            inaccuracy: Threshold::new(1.0, 0.03),
+            mistake: Threshold::new(3.0, 0.08),
+            blunder: Threshold::new(6.0, 0.15),
+        }
+    }
+
+    pub fn time(time: Duration) -> Self {
+        Self {
+            budget: Budget::Time(time),
+            max_time: time.max(Duration::from_secs(60)),
+            ..Self::visits(0)
+        }
+    }
+
+    pub fn judge(&self, point_loss: f32, winrate_loss: f32) -> Judgement {
+        if self.blunder.is_reached(point_loss, winrate_loss) {
+            Judgement::Blunder
+        } else if self.mistake.is_reached(point_loss, winrate_loss) {
+            Judgement::Mistake
+        } else if self.inaccuracy.is_reached(point_loss, winrate_loss) {
+            Judgement::Inaccuracy
+        } else {
+            Judgement::Good
+        }
+    }
+}
+
+impl Default for ReviewSettings {
+    fn default() -> Self {
+        Self::visits(200)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum Judgement {
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl Display for Judgement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", match self {
+            Self::Good => "Good",
+            Self::Inaccuracy => "Inaccuracy",
+            Self::Mistake => "Mistake",
+            Self::Blunder => "Blunder",
+        })
+    }
+}
+
+/// Evaluation of a position, from the point of view of the side to move.
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionReview {
+    /// Number of moves played before the position.
+    pub move_number: usize,
+    pub to_move: Color,
+    pub winrate: f32,
+    pub score_lead: f32,
+    pub visits: u64,
+    pub best_move: Vertex,
+    #[serde(skip)]
+    pub info: Info,
+}
+
+impl PositionReview {
+    /// Winrate and score lead from the point of view of `color`.
+    pub fn value_for(&self, color: Color) -> (f32, f32) {
+        if color == self.to_move {
+            (self.winrate, self.score_lead)
+        } else {
+            (1.0 - self.winrate, -self.score_lead)
+        }
+    }
+}
+
+/// A move compared with the best one, values being from the point of view
+/// of the player of the move.
+#[derive(Debug, Clone, Serialize)]
+pub struct MoveReview {
+    /// 1 for the first move.
+    pub move_number: usize,
+    pub played: Move,
+    pub best_move: Vertex,
+    pub winrate_before: f32,
+    pub winrate_after: f32,
+    pub score_before: f32,
+    pub score_after: f32,
+    pub point_loss: f32,
+    pub winrate_loss: f32,
+    pub judgement: Judgement,
+}
+
+/// Writes the SGF comment of the move.
+impl Display for MoveReview {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.judgement != Judgement::Good {
+            writeln!(f, "{}: lost {:.1} points ({:.1}% winrate), best move {}",
+                self.judgement, self.point_loss, self.winrate_loss * 100.0, self.best_move)?;
+        }
+        let player = match self.played.color {
+            Color::Black => "Black",
+            Color::White => "White",
+        };
+        write!(f, "{} winrate {:.1}%, score lead {:+.1}", player, self.winrate_after * 100.0, self.score_after)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GameReview {
+    /// The initial position followed by the position after each move.
+    pub positions: Vec<PositionReview>,
+    pub moves: Vec<MoveReview>,
+}
+
+impl GameReview {
+    /// The moves judged `at_least` as bad.
+    pub fn mistakes(&self, at_least: Judgement) -> Vec<&MoveReview> {
+        self.moves.iter().filter(|m| m.judgement >= at_least).collect()
+    }
+
+    /// Comments the moves of the main line of `tree`, after any existing
+    /// comment, and marks mistakes with `BM[1]` and blunders with `BM[2]`.
+    pub fn annotate(&self, tree: &mut GameTree) -> Result<(), ParseError> {
+        let size = tree.size()?;
+        let mut reviews = self.moves.iter();
+        for node in tree.main_line_mut() {
+            if node.get_move(size)?.is_none() {
+                continue;
+            }
+            let Some(review) = reviews.next() else {
+                break;
+            };
+            let comment = match node.get("C") {
+                Some(comment) => format!("{}\n\n{}", comment, review),
+                None => review.to_string(),
+            };
+            node.set("C", comment);
+            match review.judgement {
+                Judgement::Mistake => node.set("BM", "1"),
+                Judgement::Blunder => node.set("BM", "2"),
+                _ => {},
+            }
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+fn invalid_data(e: impl Display) -> Error {
+    Error::new(ErrorKind::InvalidData, e.to_string())
+}
+
+/// Analyses the position set up in the engine until the budget is spent.
+fn analyze_position(controller: &mut Controller, to_move: Color, settings: &ReviewSettings) -> Result<Info, Error> {
+    controller.discard_info();
+    controller.run(GtpCommand::KataAnalyze(AnalyzeArgs::new(Some(to_move), Some(settings.interval))))?;
+    let start = Instant::now();
+    loop {
+        let info = controller.read_info().map_err(invalid_data)?;
+        let elapsed = start.elapsed();
+        match info {
+            Some(info) => {
+                let done = match settings.budget {
+                    Budget::Visits(visits) => info.total_visits() >= visits,
+                    Budget::Time(time) => elapsed >= time,
+                };
+                if done || elapsed >= settings.max_time {
+                    return Ok(info.with_perspective(settings.perspective, Perspective::SideToMove, to_move)
+                        .ownership_from(settings.perspective, to_move));
+                }
+            },
+            None if elapsed >= settings.max_time =>
+                return Err(Error::new(ErrorKind::TimedOut, "no analysis received")),
+            None => {},
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Reviews the main line of `tree`, which is set up in the engine with
+/// [`Controller::load_sgf`]. The engine is left at the final position.
+pub fn review(controller: &mut Controller, tree: &GameTree, settings: &ReviewSettings) -> Result<GameReview, Error> {
+    let moves = tree.moves().map_err(invalid_data)?;
+    let mut game = controller.load_sgf(tree, Some(1))?;
+
+    let mut positions = Vec::new();
+    for i in 0..=moves.inner().len() {
+        let to_move = game.to_move();
+        let info = analyze_position(controller, to_move, settings)?;
+        let (winrate, score_lead, best_move) = info.best_move()
+            .map(|best| (best.winrate, best.score_lead, best.coord))
+            .ok_or_else(|| invalid_data(format!("no candidate move after move {}", i)))?;
+        positions.push(PositionReview {
+            move_number: i,
+            to_move,
+            winrate,
+            score_lead,
+            visits: info.total_visits(),
+            best_move,
+            info,
+        });
+
+        // any command ends the analysis
+        match moves.inner().get(i) {
+            Some(mov) => {
+                controller.run(GtpCommand::Play(*mov))?;
+                game.play(*mov).map_err(|_| invalid_data(format!("illegal move {}", mov)))?;
+            },
+            None => {
+                controller.run(GtpCommand::ProtocolVersion)?;
+            },
+        }
+    }
+
+    let moves = moves.inner().iter().enumerate().map(|(i, mov)| {
+        let (winrate_before, score_before) = positions[i].value_for(mov.color);
+        let (winrate_after, score_after) = positions[i + 1].value_for(mov.color);
+        let point_loss = score_before - score_after;
+        let winrate_loss = winrate_before - winrate_after;
+        MoveReview {
+            move_number: i + 1,
+            played: *mov,
+            best_move: positions[i].best_move,
+            winrate_before,
+            winrate_after,
+            score_before,
+            score_after,
+            point_loss,
+            winrate_loss,
+            judgement: settings.judge(point_loss, winrate_loss),
+        }
+    }).collect();
+
+    Ok(GameReview { positions, moves })
+}
@@ -0,0 +1,519 @@
+//! Reading and writing game records in the Smart Game Format (FF[4]).
+
+use crate::model::Color;
+use crate::model::Game;
+use crate::model::List;
+use crate::model::Move;
+use crate::model::ParseError;
+use crate::model::ParseErrorKind;
+use crate::model::RectSize;
+use crate::model::Score;
+use crate::model::Vertex;
+use alloc::string::String;
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::fmt::Display;
+use core::str::FromStr;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Property {
+    pub id: String,
+    /// Unescaped values.
+    pub values: Vec<String>,
+}
+
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Node {
+    pub properties: Vec<Property>,
+}
+
+impl Node {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// First value of the property `id`.
+    pub fn get(&self, id: &str) -> Option<&str> {
+        self.values(id).first().map(|v| v.as_str())
+    }
+
+    pub fn values(&self, id: &str) -> &[String] {
+        self.properties.iter()
+            .find(|p| p.id == id)
+            .map(|p| p.values.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Replaces the values of the property `id` with `value`.
+    pub fn set(&mut self, id: &str, value: impl Into<String>) {
+        self.remove(id);
+        self.properties.push(Property { id: id.to_string(), values: vec![value.into()] });
+    }
+
+    /// Adds `value` to the values of the property `id`.
+    pub fn push(&mut self, id: &str, value: impl Into<String>) {
+        match self.properties.iter_mut().find(|p| p.id == id) {
+            Some(p) => p.values.push(value.into()),
+            None => self.properties.push(Property { id: id.to_string(), values: vec![value.into()] }),
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.properties.retain(|p| p.id != id);
+    }
+
+    /// The move played in this node, if any.
+    pub fn get_move(&self, size: RectSize) -> Result<Option<Move>, ParseError> {
+        for color in [Color::Black, Color::White] {
+            if let Some(point) = self.get(&color.to_string()) {
+                return Ok(Some(Move { color, vertex: point_to_vertex(point, size)? }));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A sequence of nodes followed by its variations. The first variation
+/// continues the main line.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GameTree {
+    pub nodes: Vec<Node>,
+    pub variations: Vec<GameTree>,
+}
+
+/// Deepest nesting of variations accepted by [`parse_collection`], as
+/// dropping, cloning and writing trees recurse once per level.
+pub const MAX_NESTING: usize = 1000;
+
+/// Parses every game tree of an SGF collection.
+pub fn parse_collection(input: &str) -> Result<Vec<GameTree>, ParseError> {
+    let mut parser = Parser { input, pos: 0 };
+    let mut stack: Vec<GameTree> = Vec::new();
+    let mut trees = Vec::new();
+
+    loop {
+        parser.skip_whitespace();
+        match parser.peek() {
+            Some('(') => {
+                if stack.len() == MAX_NESTING {
+                    return Err(parser.error("at most 1000 nested variations"));
+                }
+                parser.bump();
+                stack.push(GameTree::default());
+            },
+            Some(';') => match stack.last_mut() {
+                Some(tree) if tree.variations.is_empty() => {
+                    parser.bump();
+                    let node = parser.node()?;
+                    tree.nodes.push(node);
+                },
+                _ => return Err(parser.error("'(' or ')'")),
+            },
+            Some(')') => {
+                let tree = match stack.pop() {
+                    Some(tree) if !tree.nodes.is_empty() => tree,
+                    _ => return Err(parser.error("';'")),
+                };
+                parser.bump();
+                match stack.last_mut() {
+                    Some(parent) => parent.variations.push(tree),
+                    None => trees.push(tree),
+                }
+            },
+            Some(_) => return Err(parser.error(if stack.is_empty() { "'('" } else { "';', '(' or ')'" })),
+            None if !stack.is_empty() => return Err(parser.error("')'")),
+            None if trees.is_empty() => return Err(ParseErrorKind::EmptyString.into()),
+            None => return Ok(trees),
+        }
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().map(char::is_whitespace).unwrap_or(false) {
+            self.bump();
+        }
+    }
+
+    fn error(&self, expected: &'static str) -> ParseError {
+        ParseError::new(ParseErrorKind::WrongSgf).expected(expected).at(self.pos)
+    }
+
+    fn node(&mut self) -> Result<Node, ParseError> {
+        let mut node = Node::new();
+        loop {
+            self.skip_whitespace();
+            match self.peek() {
+                Some(c) if c.is_ascii_alphabetic() => {
+                    let start = self.pos;
+                    let mut id = String::new();
+                    while let Some(c) = self.peek().filter(char::is_ascii_alphabetic) {
+                        // FF[3] allowed lower case letters in identifiers, they are ignored
+                        if c.is_ascii_uppercase() {
+                            id.push(c);
+                        }
+                        self.bump();
+                    }
+                    if id.is_empty() {
+                        return Err(ParseError::new(ParseErrorKind::WrongSgf).expected("a property identifier").at(start));
+                    }
+
+                    let mut values = Vec::new();
+                    self.skip_whitespace();
+                    while self.peek() == Some('[') {
+                        self.bump();
+                        values.push(self.value()?);
+                        self.skip_whitespace();
+                    }
+                    if values.is_empty() {
+                        return Err(self.error("'['"));
+                    }
+                    node.properties.push(Property { id, values });
+                },
+                _ => return Ok(node),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<String, ParseError> {
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                Some(']') => return Ok(value),
+                Some('\\') => match self.bump() {
+                    // soft line break
+                    Some('\n') => {
+                        if self.peek() == Some('\r') {
+                            self.bump();
+                        }
+                    },
+                    Some('\r') => {
+                        if self.peek() == Some('\n') {
+                            self.bump();
+                        }
+                    },
+                    Some(c) => value.push(c),
+                    None => return Err(self.error("']'")),
+                },
+                Some(c) => value.push(c),
+                None => return Err(self.error("']'")),
+            }
+        }
+    }
+}
+
+fn point_coordinate(c: u8) -> Option<usize> {
+    match c {
+        b'a'..=b'z' => Some((c - b'a') as usize),
+        b'A'..=b'Z' => Some((c - b'A') as usize + 26),
+        _ => None,
+    }
+}
+
+/// Converts an SGF point such as `pd` to a vertex. An empty point, or `tt`
+/// on boards up to 19x19, is a pass.
+pub fn point_to_vertex(point: &str, size: RectSize) -> Result<Vertex, ParseError> {
+    let wrong_point = || ParseError::new(ParseErrorKind::WrongCoordinates)
+        .expected("an SGF point like pd")
+        .with_input(point);
+
+    if point.is_empty() || (point == "tt" && size.width() <= 19 && size.height() <= 19) {
+        return Ok(Vertex::Pass);
+    }
+    match point.as_bytes() {
+        [col, row] => {
+            let col = point_coordinate(*col).ok_or_else(wrong_point)?;
+            let row = point_coordinate(*row).ok_or_else(wrong_point)?;
+            Vertex::from_row_col(row, col, size).ok_or_else(wrong_point)
+        },
+        _ => Err(wrong_point()),
+    }
+}
+
+/// Converts a vertex to an SGF point; a pass is the empty point.
+/// Resignations have no SGF point.
+pub fn vertex_to_point(vertex: Vertex, size: RectSize) -> Option<String> {
+    const LETTERS: &[u8; 52] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    match vertex {
+        Vertex::Pass => Some(String::new()),
+        Vertex::Resign => None,
+        Vertex::Coord(..) => {
+            let (row, col) = vertex.to_row_col(size)?;
+            Some([LETTERS[col] as char, LETTERS[row] as char].iter().collect())
+        },
+    }
+}
+
+/// Expands a list of points, including compressed rectangles like `aa:cc`.
+fn point_list(values: &[String], size: RectSize) -> Result<Vec<Vertex>, ParseError> {
+    let mut vertices = Vec::new();
+    for value in values {
+        match value.split_once(':') {
+            Some((from, to)) => {
+                let corner = |p| point_to_vertex(p, size)?.to_row_col(size)
+                    .ok_or_else(|| ParseError::new(ParseErrorKind::WrongCoordinates).with_input(value));
+                let (top, left) = corner(from)?;
+                let (bottom, right) = corner(to)?;
+                for row in top.min(bottom)..=top.max(bottom) {
+                    vertices.extend((left.min(right)..=left.max(right)).filter_map(|col| Vertex::from_row_col(row, col, size)));
+                }
+            },
+            None => vertices.push(point_to_vertex(value, size)?),
+        }
+    }
+    Ok(vertices)
+}
+
+fn root_value<T>(tree: &GameTree, id: &'static str) -> Result<Option<T>, ParseError>
+where T: FromStr, ParseError: From<T::Err> {
+    tree.root().and_then(|root| root.get(id))
+        .map(|v| v.trim().parse::<T>().map_err(|e| ParseError::from(e).expected(id).with_input(v)))
+        .transpose()
+}
+
+impl GameTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn root(&self) -> Option<&Node> {
+        self.nodes.first()
+    }
+
+    pub fn root_mut(&mut self) -> &mut Node {
+        if self.nodes.is_empty() {
+            self.nodes.push(Node::new());
+        }
+        &mut self.nodes[0]
+    }
+
+    /// The nodes of the main line, following the first variation at each fork.
+    pub fn main_line(&self) -> impl Iterator<Item = &Node> {
+        let mut tree = Some(self);
+        core::iter::from_fn(move || {
+            let current = tree?;
+            tree = current.variations.first();
+            Some(current.nodes.iter())
+        }).flatten()
+    }
+
+    pub fn main_line_mut(&mut self) -> impl Iterator<Item = &mut Node> {
+        let mut tree = Some(self);
+        core::iter::from_fn(move || {
+            let GameTree { nodes, variations } = tree.take()?;
+            tree = variations.first_mut();
+            Some(nodes.iter_mut())
+        }).flatten()
+    }
+
+    /// Board size from `SZ`, 19x19 when missing.
+    pub fn size(&self) -> Result<RectSize, ParseError> {
+        let value = match self.root().and_then(|root| root.get("SZ")) {
+            Some(value) => value,
+            None => return Ok(RectSize::square(19)),
+        };
+        let dimension = |v: &str| v.trim().parse::<u32>().map_err(|e| ParseError::from(e).expected("SZ").with_input(value));
+        let size = match value.split_once(':') {
+            Some((width, height)) => RectSize::new(dimension(width)?, dimension(height)?),
+            None => RectSize::square(dimension(value)?),
+        };
+        if size.is_valid() {
+            Ok(size)
+        } else {
+            Err(ParseError::new(ParseErrorKind::WrongSgf).expected("a board size up to 25x25").with_input(value))
+        }
+    }
+
+    /// Komi from `KM`, 0 when missing.
+    pub fn komi(&self) -> Result<f32, ParseError> {
+        Ok(root_value(self, "KM")?.unwrap_or(0.0))
+    }
+
+    /// Number of handicap stones from `HA`, 0 when missing.
+    pub fn handicap(&self) -> Result<u32, ParseError> {
+        Ok(root_value(self, "HA")?.unwrap_or(0))
+    }
+
+    /// Result from `RE`, `None` when missing or unknown (`RE[?]`).
+    pub fn result(&self) -> Result<Option<Score>, ParseError> {
+        if self.root().and_then(|root| root.get("RE")).is_some_and(|v| v.trim() == "?") {
+            return Ok(None);
+        }
+        root_value(self, "RE")
+    }
+
+    pub fn rules(&self) -> Option<&str> {
+        self.root().and_then(|root| root.get("RU"))
+    }
+
+    /// Setup stones of the root node, from `AB` and `AW`.
+    pub fn setup(&self) -> Result<Vec<Move>, ParseError> {
+        let size = self.size()?;
+        let mut stones = Vec::new();
+        if let Some(root) = self.root() {
+            for (id, color) in [("AB", Color::Black), ("AW", Color::White)] {
+                stones.extend(point_list(root.values(id), size)?.into_iter().map(|vertex| Move { color, vertex }));
+            }
+        }
+        Ok(stones)
+    }
+
+    /// The moves of the main line.
+    pub fn moves(&self) -> Result<List<Move>, ParseError> {
+        let size = self.size()?;
+        self.main_line().filter_map(|node| node.get_move(size).transpose()).collect()
+    }
+
+    /// Replays the main line. Setup stones are only supported in the root node.
+    pub fn to_game(&self) -> Result<Game, ParseError> {
+        let size = self.size()?;
+        let invalid = |expected| ParseError::new(ParseErrorKind::WrongSgf).expected(expected);
+        let mut game = Game::try_new(size)
+            .map_err(|_| invalid("a board size up to 25x25"))?
+            .with_komi(self.komi()?);
+
+        let setup = self.setup()?;
+        let handicap: Vec<Vertex> = setup.iter().filter(|m| m.color == Color::Black).map(|m| m.vertex).collect();
+        if self.handicap()? >= 2 && handicap.len() == setup.len() && handicap.len() >= 2 {
+            game.set_handicap(&handicap).map_err(|_| invalid("valid handicap stones"))?;
+        } else {
+            game.add_setup(&setup).map_err(|_| invalid("valid setup stones"))?;
+        }
+        if let Some(color) = root_value::<Color>(self, "PL")? {
+            game.set_to_move(color);
+        }
+
+        for (i, node) in self.main_line().enumerate() {
+            if i > 0 && ["AB", "AW", "AE"].iter().any(|id| node.get(id).is_some()) {
+                return Err(invalid("setup stones only in the root node"));
+            }
+            if let Some(mov) = node.get_move(size)? {
+                game.play(mov).map_err(|_| invalid("legal moves").with_input(&mov.to_string()))?;
+            }
+        }
+        Ok(game)
+    }
+
+    /// A record of `moves` played on an empty board.
+    pub fn from_moves(size: RectSize, komi: f32, moves: &List<Move>) -> Self {
+        let mut tree = Self::with_root(size, komi);
+        tree.push_moves(moves.inner());
+        tree
+    }
+
+    /// A record of the game so far. A final resignation is stored as the result.
+    pub fn from_game(game: &Game) -> Self {
+        let size = game.size();
+        let mut tree = Self::with_root(size, game.komi());
+        let root = tree.root_mut();
+        if !game.handicap().is_empty() {
+            root.set("HA", game.handicap().len().to_string());
+            for vertex in game.handicap() {
+                root.push("AB", vertex_to_point(*vertex, size).unwrap_or_default());
+            }
+        }
+        for stone in game.setup() {
+            let id = if stone.color == Color::Black { "AB" } else { "AW" };
+            root.push(id, vertex_to_point(stone.vertex, size).unwrap_or_default());
+        }
+        if game.moves().is_empty() && !(game.handicap().is_empty() && game.setup().is_empty()) {
+            root.set("PL", game.to_move().to_string());
+        }
+        if let Some(Move { color, vertex: Vertex::Resign }) = game.moves().last() {
+            let score = Score::Win { color: color.opponent(), margin: None, reason: crate::model::WinReason::Resign };
+            root.set("RE", score.to_string());
+        }
+
+        tree.push_moves(game.moves());
+        tree
+    }
+
+    fn with_root(size: RectSize, komi: f32) -> Self {
+        let mut root = Node::new();
+        root.set("FF", "4");
+        root.set("GM", "1");
+        root.set("CA", "UTF-8");
+        root.set("SZ", if size.width() == size.height() {
+            size.width().to_string()
+        } else {
+            alloc::format!("{}:{}", size.width(), size.height())
+        });
+        root.set("KM", komi.to_string());
+        Self { nodes: vec![root], variations: Vec::new() }
+    }
+
+    fn push_moves(&mut self, moves: &[Move]) {
+        let size = self.size().unwrap_or(RectSize::square(19));
+        for mov in moves {
+            if let Some(point) = vertex_to_point(mov.vertex, size) {
+                let mut node = Node::new();
+                node.set(&mov.color.to_string(), point);
+                self.nodes.push(node);
+            }
+        }
+    }
+}
+
+impl FromStr for GameTree {
+    type Err = ParseError;
+
+    /// Parses the first game tree of a collection.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(parse_collection(s)?.swap_remove(0))
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, ";")?;
+        for property in &self.properties {
+            write!(f, "{}", property.id)?;
+            for value in &property.values {
+                write!(f, "[")?;
+                for c in value.chars() {
+                    if c == ']' || c == '\\' {
+                        write!(f, "\\")?;
+                    }
+                    write!(f, "{}", c)?;
+                }
+                write!(f, "]")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for GameTree {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "(")?;
+        for node in &self.nodes {
+            write!(f, "{}", node)?;
+        }
+        for variation in &self.variations {
+            write!(f, "\n{}", variation)?;
+        }
+        write!(f, ")")
+    }
+}
@@ -0,0 +1,181 @@
+//! Recording sessions with an engine and replaying them.
+//!
+//! A [`Transcript`] is written in the [`mock`](crate::mock) script format:
+//! every command sent, followed by the raw answer lines, the engine's stderr
+//! and `@delay` lines for the time the engine took. Replaying it with
+//! [`Replay`] gives the same answers with the same timing, and checks the
+//! client sends the same commands.
+
+use crate::mock::MockEngine;
+use crate::model::ParseError;
+use crate::Engine;
+use std::fmt;
+use std::fmt::Display;
+use std::io::BufReader;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// Something that went through one of the engine's streams.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranscriptEvent {
+    /// A command line sent to the engine.
+    Sent(String),
+    /// A line the engine wrote on stdout, including info lines.
+    Received(String),
+    /// A line the engine wrote on stderr.
+    Stderr(String),
+}
+
+/// An event and when it happened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TranscriptEntry {
+    /// Time since the recording started.
+    pub at: Duration,
+    pub event: TranscriptEvent,
+}
+
+/// Recording of a session with an engine, see
+/// [`Engine::record`](crate::Engine::record).
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    start: Instant,
+    entries: Vec<TranscriptEntry>,
+    /// start of a command line whose end was not sent yet
+    pending: String,
+}
+
+impl Default for Transcript {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Transcript {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            entries: Vec::new(),
+            pending: String::new(),
+        }
+    }
+
+    pub fn entries(&self) -> &[TranscriptEntry] {
+        &self.entries
+    }
+
+    fn push(&mut self, event: TranscriptEvent) {
+        self.entries.push(TranscriptEntry { at: self.start.elapsed(), event });
+    }
+
+    /// Records text written to the engine, one entry per complete line.
+    pub(crate) fn sent(&mut self, text: &str) {
+        self.pending.push_str(text);
+        while let Some(end) = self.pending.find('\n') {
+            let line: String = self.pending.drain(..=end).collect();
+            self.push(TranscriptEvent::Sent(line.trim_end().to_string()));
+        }
+    }
+
+    pub(crate) fn received(&mut self, line: &str) {
+        self.push(TranscriptEvent::Received(line.to_string()));
+    }
+
+    pub(crate) fn stderr(&mut self, line: &str) {
+        self.push(TranscriptEvent::Stderr(line.to_string()));
+    }
+
+    /// A mock engine answering like the recorded one. Fails when a recorded
+    /// line reads as a script directive, e.g. engine output starting with `>`.
+    pub fn to_mock(&self) -> Result<MockEngine, ParseError> {
+        self.to_string().parse()
+    }
+
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), std::io::Error> {
+        std::fs::write(path, self.to_string())
+    }
+}
+
+/// Removes the id following `=` or `?`, the replay adds the one of the command answered.
+fn strip_id(line: &str) -> String {
+    match line.chars().next() {
+        Some(c @ ('=' | '?')) => {
+            let rest = line[1..].trim_start_matches(|c: char| c.is_ascii_digit());
+            format!("{}{}", c, rest)
+        },
+        _ => line.to_string(),
+    }
+}
+
+impl Display for Transcript {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# libgtp transcript")?;
+        let mut previous = Duration::ZERO;
+        let mut answering = false;
+        for entry in &self.entries {
+            // empty lines terminating answers are added back by the replay
+            if entry.event == TranscriptEvent::Received(String::new()) {
+                continue;
+            }
+            // the time before a command is the client's, only the engine's is replayed
+            if !matches!(entry.event, TranscriptEvent::Sent(_)) {
+                let delay = entry.at.saturating_sub(previous);
+                if delay >= Duration::from_millis(1) {
+                    writeln!(f, "@delay {}", delay.as_millis())?;
+                }
+            }
+            previous = entry.at;
+
+            match &entry.event {
+                TranscriptEvent::Sent(command) => {
+                    writeln!(f, "> {}", command)?;
+                    answering = true;
+                },
+                TranscriptEvent::Received(line) if answering => {
+                    writeln!(f, "{}", strip_id(line))?;
+                    answering = false;
+                },
+                TranscriptEvent::Received(line) => writeln!(f, "{}", line)?,
+                TranscriptEvent::Stderr(line) => writeln!(f, "@stderr {}", line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A recorded session played back as an engine.
+#[derive(Debug)]
+pub struct Replay {
+    thread: JoinHandle<Result<MockEngine, std::io::Error>>,
+}
+
+impl Replay {
+    /// Starts an in-process engine answering with `script`, usually a
+    /// [`Transcript`] turned into a [`MockEngine`].
+    pub fn start(script: MockEngine) -> Result<(Engine, Self), std::io::Error> {
+        let (commands, stdin) = std::io::pipe()?;
+        let (stdout, output) = std::io::pipe()?;
+        let (stderr, errors) = std::io::pipe()?;
+
+        let thread = std::thread::spawn(move || {
+            let mut script = script;
+            script.run(BufReader::new(commands), output, errors)?;
+            Ok(script)
+        });
+
+        Ok((Engine::from_streams(stdin, stdout, stderr).start(), Self { thread }))
+    }
+
+    /// Waits for the session to end, once the engine got `quit` or was
+    /// dropped, and checks the recorded commands were all sent, in order.
+    pub fn finish(self) -> Result<(), std::io::Error> {
+        let script = self.thread.join()
+            .map_err(|_| std::io::Error::other("replay panicked"))??;
+        if !script.mismatches().is_empty() {
+            return Err(std::io::Error::other(format!("unexpected commands: {}", script.mismatches().join(", "))));
+        }
+        if !script.is_finished() {
+            return Err(std::io::Error::other("some recorded commands were not sent"));
+        }
+        Ok(())
+    }
+}
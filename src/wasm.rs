@@ -0,0 +1,62 @@
+//! WebAssembly bindings for the parsers, enabled by the `wasm` feature.
+//!
+//! Parsed values are plain JS objects shaped like their `serde` JSON, e.g.
+//! `parseInfo("info move D4 visits 10 ...").explored_moves[0].coord` is
+//! `"D4"`. Parse errors are thrown with their message.
+
+use crate::model::{Answer, Command, CommandName, Info, ParseError};
+use alloc::string::{String, ToString};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsError> {
+    value.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, JsError> {
+    serde_wasm_bindgen::from_value(value).map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[wasm_bindgen(js_name = parseCommand)]
+pub fn parse_command(text: &str) -> Result<JsValue, JsError> {
+    let command = text.parse::<Command>().map_err(|e| JsError::new(&e.to_string()))?;
+    to_js(&command)
+}
+
+/// The command line, terminated by a newline.
+#[wasm_bindgen(js_name = formatCommand)]
+pub fn format_command(command: JsValue) -> Result<String, JsError> {
+    Ok(from_js::<Command>(command)?.to_string())
+}
+
+/// The answer [`parse_answer`] converts.
+pub(crate) fn answer(text: &str, command: Option<String>) -> Result<Answer, ParseError> {
+    match command {
+        Some(name) => Answer::parse_for(CommandName::from(name), text),
+        None => Answer::parse_answer(text),
+    }
+}
+
+/// Parses an answer, decoding its data for `command` when given, e.g. `"genmove"`.
+#[wasm_bindgen(js_name = parseAnswer)]
+pub fn parse_answer(text: &str, command: Option<String>) -> Result<JsValue, JsError> {
+    to_js(&answer(text, command).map_err(|e| JsError::new(&e.to_string()))?)
+}
+
+#[wasm_bindgen(js_name = formatAnswer)]
+pub fn format_answer(answer: JsValue) -> Result<String, JsError> {
+    Ok(from_js::<Answer>(answer)?.to_string())
+}
+
+#[wasm_bindgen(js_name = parseInfo)]
+pub fn parse_info(text: &str) -> Result<JsValue, JsError> {
+    let info = text.parse::<Info>().map_err(|e| JsError::new(&e.to_string()))?;
+    to_js(&info)
+}
+
+#[wasm_bindgen(js_name = formatInfo)]
+pub fn format_info(info: JsValue) -> Result<String, JsError> {
+    Ok(from_js::<Info>(info)?.to_string())
+}
@@ -0,0 +1,277 @@
+#![cfg(feature = "controller")]
+
+use libgtp::mock::{MockEngine, MockExit};
+use libgtp::model::*;
+use libgtp::sgf::GameTree;
+use libgtp::{Controller, Engine};
+use std::io::{Cursor, ErrorKind};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// A file of the temporary directory, removed when dropped.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        Self(std::env::temp_dir().join(format!("libgtp-{}-{}", std::process::id(), name)))
+    }
+}
+
+impl Deref for TempFile {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn script(name: &str, content: &str) -> TempFile {
+    let file = TempFile::new(&format!("{}.gtp", name));
+    std::fs::write(&*file, content).unwrap();
+    file
+}
+
+/// The controller of a mock engine, and its script to keep until the end of the test.
+fn mock(name: &str, content: &str) -> (Controller, TempFile) {
+    let path = script(name, content);
+    let engine = Engine::new(env!("CARGO_BIN_EXE_libgtp-mock-engine"), &[path.to_str().unwrap()])
+        .unwrap()
+        .with_ready_line(None)
+        .start();
+    (Controller::from_engine(engine), path)
+}
+
+#[test]
+fn test_mock_engine() {
+    let mut engine: MockEngine = "@stderr ready\n> name\n= mock\n> 7 list_commands\n= name\nplay\n\
+                                  > kata-analyze 10\n=\ninfo move D4 visits 1\n> play B D4\n".parse().unwrap();
+    let input = "name\n3 list_commands\nkata-analyze   10\n2 genmove b\n4 play B D4\nquit\n";
+    let (mut output, mut errors) = (Vec::new(), Vec::new());
+    assert_eq!(MockExit::Eof, engine.run(Cursor::new(input), &mut output, &mut errors).unwrap());
+
+    assert_eq!("ready\n", String::from_utf8(errors).unwrap());
+    assert_eq!("= mock\n\n=3 name\nplay\n\n=\ninfo move D4 visits 1\n\n\
+                ?2 unexpected command genmove b, expected play B D4\n\n=4\n\n\
+                ? unexpected command quit, end of script\n\n", String::from_utf8(output).unwrap());
+    assert_eq!(&["genmove b".to_string(), "quit".to_string()], engine.mismatches());
+    assert!(engine.is_finished());
+    assert!("@unknown".parse::<MockEngine>().is_err());
+    assert!("> name\n@delay soon".parse::<MockEngine>().is_err());
+}
+
+#[test]
+fn test_controller() {
+    let (mut controller, _script) = mock("controller", "\
+        > name\n= mock\n\
+        > 1 genmove B\n@delay 20\n= D4\n\
+        > play W D4\n? illegal move\n\
+        > play W Q16\n\
+        > list_commands\n= name\nplay\nset_position\n\
+        > boardsize 9\n> clear_board\n> komi 7\n> set_position B C7 W G3\n> play B E5\n\
+        > play W E5\n\
+        > quit\n");
+
+    let answer = controller.send_command("name".parse().unwrap()).unwrap();
+    assert_eq!("mock", answer.to_response().unwrap().data().as_ref().unwrap().to_string().trim());
+
+    controller.enable_mirroring();
+    let answer = controller.send_command(GtpCommand::Genmove(Color::Black).with_id(1)).unwrap();
+    let response = answer.to_response().unwrap();
+    assert_eq!(&Some(1), response.id());
+    assert_eq!(Some(&"D4".parse().unwrap()), response.data().as_ref().unwrap().as_vertex());
+
+    let answer = controller.send_command("play W D4".parse().unwrap()).unwrap();
+    assert_eq!("illegal move", answer.to_failure().unwrap().message());
+    controller.run(GtpCommand::Play("W Q16".parse().unwrap())).unwrap();
+    assert_eq!(Some(2), controller.move_number());
+    assert!(controller.is_in_sync());
+
+    let tree: GameTree = "(;SZ[9]KM[7]AB[cc]AW[gg];B[ee])".parse().unwrap();
+    let game = controller.load_sgf(&tree, None).unwrap();
+    assert_eq!(Some(&game), controller.mirror());
+    assert!(controller.supports(CommandName::SetPosition).unwrap());
+    controller.run(GtpCommand::Play("W E5".parse().unwrap())).unwrap();
+    assert!(!controller.is_in_sync());
+    controller.disable_mirroring();
+    assert!(controller.is_in_sync());
+
+    controller.run(GtpCommand::Quit).unwrap();
+}
+
+#[test]
+fn test_controller_analysis() {
+    let (mut controller, _script) = mock("analysis", "\
+        > kata-analyze 10\n=\n\
+        info move D4 visits 10 winrate 0.5 pv D4 Q16\n@delay 20\n\
+        info move Q16 visits 20 winrate 0.4 pv Q16\n\
+        > name\n= mock\n");
+
+    let answer = controller.send_command("kata-analyze 10".parse().unwrap()).unwrap();
+    assert!(answer.is_response());
+
+    let start = Instant::now();
+    let mut last = None;
+    while start.elapsed() < Duration::from_secs(5) {
+        if let Some(info) = controller.read_info().unwrap() {
+            if info.explored_moves[0].visits == 20 {
+                last = Some(info);
+                break;
+            }
+        }
+    }
+    assert_eq!(Some("Q16".parse().unwrap()), last.map(|i| i.explored_moves[0].coord));
+
+    // the answer terminating the analysis is skipped
+    let answer = controller.send_command("name".parse().unwrap()).unwrap();
+    assert!(answer.is_response());
+}
+
+#[test]
+fn test_controller_parse_error() {
+    let (mut controller, _script) = mock("parse-error", "> genmove B\n= Z99\n");
+    let err = controller.send_command(GtpCommand::Genmove(Color::Black).into()).unwrap_err();
+    assert_eq!(ErrorKind::InvalidData, err.kind());
+    let parse_error = err.get_ref().unwrap().downcast_ref::<ParseError>().unwrap();
+    assert_eq!(Some("Z99"), parse_error.input());
+    assert_eq!(parse_error.to_string(), err.to_string());
+}
+
+#[test]
+fn test_controller_ready_line_and_crash() {
+    let path = script("crash", "@delay 50\n@stderr GTP ready, beginning main protocol loop\n> name\n= mock\n> *\n@crash\n");
+    let engine = Engine::new(env!("CARGO_BIN_EXE_libgtp-mock-engine"), &[path.to_str().unwrap()]).unwrap().start();
+    let mut controller = Controller::from_engine(engine);
+
+    assert!(controller.send_command("name".parse().unwrap()).unwrap().is_response());
+    let err = controller.send_command("genmove b".parse().unwrap()).unwrap_err();
+    assert_eq!(ErrorKind::UnexpectedEof, err.kind());
+}
+
+#[test]
+fn test_transcript_replay() {
+    use libgtp::transcript::{Replay, TranscriptEvent};
+
+    let path = script("record", "@stderr loading\n> name\n= mock\n> genmove B\n@stderr thinking\n@delay 30\n= Q16\n\
+                                 > kata-analyze 10\n=\ninfo move D4 visits 3\n> list_commands\n= name\ngenmove\n");
+    let mut engine = Engine::new(env!("CARGO_BIN_EXE_libgtp-mock-engine"), &[path.to_str().unwrap()]).unwrap()
+        .with_ready_line(None);
+    engine.record();
+    let mut controller = Controller::from_engine(engine.start());
+
+    let session = |controller: &mut Controller| {
+        controller.run(GtpCommand::Name).unwrap();
+        controller.run(GtpCommand::Genmove(Color::Black).with_id(4)).unwrap();
+        controller.send_command("kata-analyze 10".parse().unwrap()).unwrap();
+        let start = Instant::now();
+        while controller.read_info().unwrap().is_none() && start.elapsed() < Duration::from_secs(5) {}
+        controller.run(GtpCommand::ListCommands).unwrap()
+    };
+    let recorded = session(&mut controller);
+    let transcript = controller.take_transcript().unwrap();
+    drop(controller);
+
+    let text = transcript.to_string();
+    assert!(text.contains("@stderr loading\n") && text.contains("@stderr thinking\n"), "{}", text);
+    // stderr and delays fall between stdout lines depending on timing
+    let exchanges: Vec<&str> = text.lines().filter(|l| !l.starts_with('@')).collect();
+    let exchanges = exchanges.join("\n");
+    assert!(exchanges.contains("= mock\n> 4 genmove B\n"), "{}", text);
+    assert!(exchanges.contains("= Q16\n> kata-analyze 10\n=\ninfo move D4 visits 3\n> list_commands\n"), "{}", text);
+    let sent = transcript.entries().iter().position(|e| e.event == TranscriptEvent::Sent("4 genmove B".into())).unwrap();
+    let answered = transcript.entries().iter().position(|e| e.event == TranscriptEvent::Received("=4 Q16".into())).unwrap();
+    assert!(transcript.entries()[answered].at - transcript.entries()[sent].at >= Duration::from_millis(30));
+
+    // the replay answers the same, and checks the same commands are sent
+    let (engine, replay) = Replay::start(transcript.to_mock().unwrap()).unwrap();
+    let mut controller = Controller::from_engine(engine);
+    assert_eq!(recorded.to_string(), session(&mut controller).to_string());
+    drop(controller);
+    replay.finish().unwrap();
+
+    let (engine, replay) = Replay::start(transcript.to_mock().unwrap()).unwrap();
+    let mut controller = Controller::from_engine(engine);
+    controller.run(GtpCommand::Name).unwrap();
+    assert!(controller.send_command(GtpCommand::Genmove(Color::White).into()).unwrap().is_failure());
+    drop(controller);
+    assert!(replay.finish().is_err());
+
+    let mut engine = Engine::from_streams(std::io::sink(), Cursor::new("= ok\n\n@oops\n"), std::io::empty());
+    engine.record();
+    let mut controller = Controller::from_engine(engine.start());
+    controller.run(GtpCommand::Name).unwrap();
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(controller.take_transcript().unwrap().to_mock().is_err());
+}
+
+#[test]
+fn test_controller_time() {
+    let (mut controller, _script) = mock("time", "\
+        > list_commands\n= name\nkgs-time_settings\n\
+        > kgs-time_settings canadian 300 30 10\n\
+        > time_settings 60 0 0\n\
+        > time_left W 40 0\n");
+
+    controller.send_time_settings("canadian 300 30 10".parse().unwrap()).unwrap();
+    let fischer: TimeControl = "fischer 60 10".parse().unwrap();
+    controller.send_time_settings(fischer).unwrap();
+    let mut clock = GameClock::new(fischer);
+    clock.record_move(Color::White, Duration::from_secs(30));
+    controller.send_time_left(&clock, Color::White).unwrap();
+    controller.send_time_left(&GameClock::new(TimeControl::None), Color::Black).unwrap();
+}
+
+#[test]
+fn test_proxy_relays_everything() {
+    use std::io::{Read, Write};
+    use std::process::{Command, Stdio};
+
+    let path = script("proxy", "\
+        > foo-bar 1\n= ok\n\
+        > kata-analyze 10\n=\ninfo move D4 visits 1\ninfo move D4 visits 2\ninfo move D4 visits 3\n\
+        > genmove b\n= D4\n\
+        > quit\n");
+    let log = TempFile::new("proxy.log");
+    let mut proxy = Command::new(env!("CARGO_BIN_EXE_libgtp-proxy"))
+        .args(["--log", log.to_str().unwrap()])
+        .args(["--", env!("CARGO_BIN_EXE_libgtp-mock-engine"), path.to_str().unwrap()])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = proxy.stdin.take().unwrap();
+    stdin.write_all(b"foo-bar 1\nkata-analyze 10\n").unwrap();
+    stdin.flush().unwrap();
+    std::thread::sleep(Duration::from_millis(100));
+    stdin.write_all(b"genmove b\nquit\n").unwrap();
+    drop(stdin);
+
+    let mut output = String::new();
+    proxy.stdout.take().unwrap().read_to_string(&mut output).unwrap();
+    assert!(proxy.wait().unwrap().success());
+    assert_eq!("= ok\n\n=\ninfo move D4 visits 1\ninfo move D4 visits 2\ninfo move D4 visits 3\n\n= D4\n\n=\n\n", output);
+}
+
+#[test]
+fn test_match_forfeit() {
+    let setup = "> boardsize 9\n> clear_board\n> komi 7.5\n";
+    let first = script("match-first", &format!("> name\n= a\n{setup}> genmove B\n? out of memory\n{setup}> quit\n"));
+    let second = script("match-second", &format!("> name\n= b\n{setup}{setup}> genmove B\n= resign\n> quit\n"));
+    let engine = env!("CARGO_BIN_EXE_libgtp-mock-engine");
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_libgtp-match"))
+        .args(["--games", "2", "--size", "9"])
+        .args(["--first", &format!("{} {}", engine, first.display())])
+        .args(["--second", &format!("{} {}", engine, second.display())])
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(stdout.starts_with("game 1: a (B) vs b (W): W+F\ngame 2: b (B) vs a (W): W+R\n"), "{}", stdout);
+    assert!(stdout.contains("a: 1 wins\nb: 1 wins\n"), "{}", stdout);
+}
@@ -0,0 +1,77 @@
+#![cfg(feature = "kata-analysis")]
+
+use libgtp::kata_analysis::{AnalysisClient, AnalysisMessage, AnalysisQuery};
+use libgtp::mock::MockEngine;
+use libgtp::model::*;
+use libgtp::transcript::Replay;
+
+#[test]
+fn test_kata_analysis() {
+    let mut game = Game::new(RectSize::square(9)).with_komi(7.0);
+    game.set_handicap(&["C3".parse().unwrap(), "G7".parse().unwrap()]).unwrap();
+    game.play("W E5".parse().unwrap()).unwrap();
+    let query = AnalysisQuery::from_game("q", &game).with_all_turns().with_max_visits(10);
+    assert_eq!(
+        r#"{"id":"q","initialStones":[["B","C3"],["B","G7"]],"initialPlayer":"W","moves":[["W","E5"]],"rules":"tromp-taylor","komi":7.0,"boardXSize":9,"boardYSize":9,"analyzeTurns":[0,1],"maxVisits":10,"includeOwnership":false,"includeMovesOwnership":false,"includePolicy":false,"includePVVisits":false}"#,
+        serde_json::to_string(&query).unwrap(),
+    );
+
+    let mut ownership = vec![0.0; 81];
+    ownership[0] = 0.5;
+    let ownership = serde_json::to_string(&ownership).unwrap();
+    let script = format!(r#"
+> {}
+{{"id":"q","isDuringSearch":false,"turnNumber":1,"moveInfos":[{{"move":"C7","visits":6,"winrate":0.3,"scoreLead":-2.5,"order":1,"pv":["C7"]}},{{"move":"D4","visits":4,"winrate":0.2,"scoreLead":-3.5,"order":0,"pv":["D4","F6"],"weight":3.6,"ownership":{ownership}}}],"rootInfo":{{"winrate":0.25,"scoreLead":-3.0,"visits":10,"currentPlayer":"B"}},"ownership":{ownership}}}
+{{"id":"other","isDuringSearch":false,"turnNumber":0,"moveInfos":[]}}
+{{"id":"q","isDuringSearch":true,"turnNumber":0,"moveInfos":[]}}
+{{"id":"q","warning":"unused field","field":"foo"}}
+{{"id":"q","isDuringSearch":false,"turnNumber":0,"moveInfos":[{{"move":"pass","visits":10,"winrate":0.9}}]}}
+> *
+{{"id":"bad","error":"Could not parse rules","field":"rules"}}
+"#, serde_json::to_string(&query).unwrap(), ownership = ownership);
+    let (engine, replay) = Replay::start(script.parse::<MockEngine>().unwrap()).unwrap();
+    let mut client = AnalysisClient::from_engine(engine);
+
+    let results = client.analyze(&query).unwrap();
+    assert_eq!(vec![0, 1], results.iter().map(|r| r.turn_number).collect::<Vec<_>>());
+    assert_eq!(Vertex::Pass, results[0].move_infos[0].coord);
+    let best = results[1].best_move().unwrap();
+    assert_eq!("D4", best.coord.to_string());
+    assert_eq!(vec!["D4".parse::<Vertex>().unwrap(), "F6".parse().unwrap()], best.pv);
+    assert_eq!(4, best.weight);
+    assert_eq!(Some(Color::Black), results[1].root_info.current_player);
+    assert_eq!(2, results[1].to_info().explored_moves.len());
+    let a9 = "A9".parse().unwrap();
+    assert_eq!(Some(0.5), results[1].to_info().ownership.unwrap().get(a9));
+    assert_eq!(Some(0.5), best.ownership.as_ref().unwrap().get(a9));
+    assert_eq!(None, results[1].move_infos[0].ownership);
+
+    // answers to other queries are kept for later
+    match client.next_message().unwrap() {
+        AnalysisMessage::Result(result) => assert_eq!("other", result.id),
+        message => panic!("unexpected {:?}", message),
+    }
+
+    let bad = AnalysisQuery::new("bad", RectSize::square(19)).with_rules("nonsense");
+    let error = client.analyze(&bad).unwrap_err();
+    assert!(error.to_string().contains("Could not parse rules (rules)"), "{}", error);
+
+    drop(client);
+    replay.finish().unwrap();
+
+    let message: AnalysisMessage = r#"{"action":"terminate","id":"t","terminateId":"q","turnNumbers":[3]}"#.parse().unwrap();
+    assert_eq!(Some("q"), message.id());
+    assert!("{}".parse::<AnalysisMessage>().is_err());
+
+    // 9 columns and 13 rows, not the 13x9 board the number of values suggests
+    let mut values = vec![0.0; 117];
+    values[9] = 1.0;
+    let line = format!(r#"{{"id":"r","turnNumber":0,"moveInfos":[],"ownership":{:?}}}"#, values);
+    let result = match line.parse::<AnalysisMessage>().unwrap() {
+        AnalysisMessage::Result(result) => result,
+        message => panic!("unexpected {:?}", message),
+    };
+    assert_eq!(None, result.to_info().ownership);
+    let ownership = result.with_board_size(RectSize::new(9, 13)).to_info().ownership.unwrap();
+    assert_eq!(Some(1.0), ownership.get("A12".parse().unwrap()));
+}
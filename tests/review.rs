@@ -0,0 +1,51 @@
+#![cfg(feature = "review")]
+
+use libgtp::mock::MockEngine;
+use libgtp::review::{review, Judgement, ReviewSettings};
+use libgtp::sgf::parse_collection;
+use libgtp::transcript::Replay;
+use libgtp::Controller;
+
+#[test]
+fn test_review() {
+    let script = "\
+        > list_commands\n= name\nplay\nkata-analyze\n\
+        > boardsize 9\n> clear_board\n> komi 7\n\
+        > kata-analyze B 10\n=\n\
+        info move D4 visits 30 winrate 0.6 scoreLead 2 order 0 pv D4 info move E5 visits 20 winrate 0.58 scoreLead 1.5 order 1 pv E5\n\
+        > play B E5\n\
+        > kata-analyze W 10\n=\n\
+        info move C3 visits 50 winrate 0.45 scoreLead 3 order 0 pv C3\n\
+        > play W C3\n\
+        > kata-analyze B 10\n=\n\
+        info move D4 visits 50 winrate 0.52 scoreLead -2.5 order 0 pv D4\n\
+        > protocol_version\n= 2\n";
+    let (engine, replay) = Replay::start(script.parse::<MockEngine>().unwrap()).unwrap();
+    let mut controller = Controller::from_engine(engine);
+
+    let mut trees = parse_collection("(;SZ[9]KM[7];B[ee];W[cg]C[nice])").unwrap();
+    let game_review = review(&mut controller, &trees[0], &ReviewSettings::visits(40)).unwrap();
+    drop(controller);
+    replay.finish().unwrap();
+
+    assert_eq!(3, game_review.positions.len());
+    assert_eq!(50, game_review.positions[0].visits);
+    let first = &game_review.moves[0];
+    assert_eq!("D4", first.best_move.to_string());
+    assert_eq!((2.0, -3.0), (first.score_before, first.score_after));
+    assert_eq!(5.0, first.point_loss);
+    assert_eq!(Judgement::Mistake, first.judgement);
+    assert_eq!(Judgement::Good, game_review.moves[1].judgement);
+    assert_eq!(1, game_review.mistakes(Judgement::Inaccuracy).len());
+
+    game_review.annotate(&mut trees[0]).unwrap();
+    let nodes: Vec<_> = trees[0].main_line().collect();
+    assert!(nodes[1].get("C").unwrap().starts_with("Mistake: lost 5.0 points"), "{:?}", nodes[1]);
+    assert_eq!(Some("1"), nodes[1].get("BM"));
+    assert_eq!(Some("nice\n\nWhite winrate 48.0%, score lead +2.5"), nodes[2].get("C"));
+    assert_eq!(None, nodes[2].get("BM"));
+
+    let json: serde_json::Value = serde_json::from_str(&game_review.to_json().unwrap()).unwrap();
+    assert_eq!("B E5", json["moves"][0]["played"]);
+    assert_eq!("Mistake", json["moves"][0]["judgement"]);
+}